@@ -1,6 +1,8 @@
 use node_template_runtime::{
-	AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig, Signature, SudoConfig,
-	SystemConfig, WASM_BINARY,
+	pallet_template::{ImageId, ProgramKind},
+	AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig, RollupTreasuryAccount,
+	Signature, SudoConfig, SystemConfig, TemplateModule, TemplateModuleConfig, EXISTENTIAL_DEPOSIT,
+	WASM_BINARY,
 };
 use sc_service::ChainType;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
@@ -11,6 +13,12 @@ use sp_runtime::traits::{IdentifyAccount, Verify};
 // The URL for the telemetry server.
 // const STAGING_TELEMETRY_URL: &str = "wss://telemetry.polkadot.io/submit/";
 
+/// Image id of the transfer guest in `provers/transfer`, registered at genesis. It is printed by
+/// the prover host, and kept here rather than built, so the node does not depend on the guest.
+/// Newer guests are registered through `TemplateModule::register_program` instead.
+const TRANSFER_IMAGE_ID: ImageId =
+	[457935672, 3918823227, 3023842824, 259760991, 4288050222, 1848304219, 1146781152, 3382653746];
+
 /// Specialized `ChainSpec`. This is a specialization of the general Substrate ChainSpec type.
 pub type ChainSpec = sc_service::GenericChainSpec<GenesisConfig>;
 
@@ -138,8 +146,18 @@ fn testnet_genesis(
 			code: wasm_binary.to_vec(),
 		},
 		balances: BalancesConfig {
-			// Configure endowed accounts with initial balance of 1 << 60.
-			balances: endowed_accounts.iter().cloned().map(|k| (k, 1 << 60)).collect(),
+			// Configure endowed accounts with initial balance of 1 << 60. The rollup's treasury and
+			// bridge accounts get the existential deposit, so they exist to be paid into.
+			balances: endowed_accounts
+				.iter()
+				.cloned()
+				.map(|k| (k, 1 << 60))
+				.chain(
+					[RollupTreasuryAccount::get(), TemplateModule::bridge_account()]
+						.into_iter()
+						.map(|k| (k, EXISTENTIAL_DEPOSIT)),
+				)
+				.collect(),
 		},
 		aura: AuraConfig {
 			authorities: initial_authorities.iter().map(|x| (x.0.clone())).collect(),
@@ -152,5 +170,10 @@ fn testnet_genesis(
 			key: Some(root_key),
 		},
 		transaction_payment: Default::default(),
+		template_module: TemplateModuleConfig {
+			programs: vec![(TRANSFER_IMAGE_ID, ProgramKind::Transfer)],
+			// Anyone may submit proofs until governance adds sequencers
+			sequencers: vec![],
+		},
 	}
 }
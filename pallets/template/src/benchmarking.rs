@@ -2,18 +2,271 @@
 
 use super::*;
 
+use crate::transfer::{encode_journal, queue_hash};
 #[allow(unused)]
 use crate::Pallet as Template;
-use frame_benchmarking::v1::{benchmarks, whitelisted_caller};
+use codec::{Decode, Encode};
+use frame_benchmarking::{account, v2::*, whitelisted_caller};
+use frame_support::{
+	sp_runtime::traits::Zero,
+	traits::{Currency, EnsureOrigin},
+	BoundedVec,
+};
 use frame_system::RawOrigin;
+use sp_std::{vec, vec::Vec};
 
-benchmarks! {
-	do_something {
-		let s in 0 .. 100;
+/// SCALE encoded `(image id, receipt)` pairs proven by the transfer guest, with 1 to 4 segments.
+/// See `fixtures/README.md` for how to regenerate them
+const SEGMENT_FIXTURES: [&[u8]; 4] = [
+	include_bytes!("../fixtures/transfer_1_segment.scale"),
+	include_bytes!("../fixtures/transfer_2_segments.scale"),
+	include_bytes!("../fixtures/transfer_3_segments.scale"),
+	include_bytes!("../fixtures/transfer_4_segments.scale"),
+];
+
+const INITIAL_BALANCE: u128 = 1 << 60;
+const TRANSFER_AMOUNT: u128 = 1_000_000_000;
+
+fn fixture(segments: u32) -> (ImageId, Receipt) {
+	let mut bytes = SEGMENT_FIXTURES[segments as usize - 1];
+	Decode::decode(&mut bytes).expect("fixtures are SCALE encoded (image id, receipt) pairs")
+}
+
+fn account_id<T: Config>(account: &T::AccountId) -> [u8; 32] {
+	let mut id = [0u8; 32];
+	let encoded = account.encode();
+	id[..encoded.len()].copy_from_slice(&encoded);
+	id
+}
+
+/// Fill the transfer queue with `withdrawals` withdrawals, which are the most expensive kind to
+/// process as each is paid out on-chain
+fn fill_queue<T: Config>(withdrawals: u32) {
+	let queue: Vec<_> = (0..withdrawals)
+		.map(|i| {
+			let account = account_id::<T>(&account("withdrawer", i, 0));
+			QueuedTransfer {
+				kind: QueuedKind::Withdrawal,
+				sender: account,
+				recipient: account,
+				amount: TRANSFER_AMOUNT,
+				queued_at: Zero::zero(),
+			}
+		})
+		.collect();
+	TransferQueue::<T>::put(BoundedVec::truncate_from(queue));
+}
+
+/// Journal of a batch changing `accounts` ledger accounts and processing the whole transfer queue
+/// along with `transactions` transactions from outside of it, which pays out every queued
+/// withdrawal and a fee per transaction to a prover and the treasury. The locked funds are topped
+/// up to cover the payouts
+fn transfer_journal<T: Config>(accounts: u32, transactions: u32) -> Vec<u8>
+where
+	BalanceOf<T>: From<u128> + Into<u128>,
+{
+	let fee: u128 = T::TransferFee::get().into();
+	let prover: T::AccountId = account("prover", 0, 0);
+	T::Currency::make_free_balance_be(&prover, T::Currency::minimum_balance());
+	T::Currency::make_free_balance_be(&T::TreasuryAccount::get(), T::Currency::minimum_balance());
+
+	let queue = TransferQueue::<T>::get();
+	let exits: Vec<_> = queue
+		.iter()
+		.map(|withdrawal| (withdrawal.recipient, withdrawal.amount.to_be_bytes()))
+		.collect();
+	let outcomes = vec![transfer_core::TRANSFER_APPLIED; queue.len() + transactions as usize];
+	let fees = fee * outcomes.len() as u128;
+	T::Currency::make_free_balance_be(
+		&Template::<T>::bridge_account(),
+		(INITIAL_BALANCE + TRANSFER_AMOUNT * exits.len() as u128 + fees).into(),
+	);
+
+	let ids = (0..accounts).map(|i| account_id::<T>(&account("account", i, 0))).collect();
+	let new_balances = (0..accounts).map(|_| INITIAL_BALANCE.to_be_bytes()).collect();
+	let new_nonces = (0..accounts).map(|_| 1).collect();
+
+	encode_journal(&(
+		ids,
+		new_balances,
+		new_nonces,
+		StateRoot::<T>::get(),
+		[1u8; 32],
+		BatchNumber::<T>::get(),
+		account_id::<T>(&prover),
+		fee.to_be_bytes(),
+		fees.to_be_bytes(),
+		queue.len() as u64,
+		queue_hash(&queue),
+		exits,
+		outcomes,
+		frame_system::Pallet::<T>::block_hash(T::BlockNumber::zero())
+			.as_ref()
+			.try_into()
+			.expect("hashes are 32 bytes"),
+		T::PalletId::get().0,
+	))
+}
+
+#[benchmarks(where BalanceOf<T>: From<u128> + Into<u128>)]
+mod benchmarks {
+	use super::*;
+
+	#[benchmark]
+	fn verify_receipt(s: Linear<1, 4>) {
+		let (image_id, receipt) = fixture(s);
+		ProgramRegistry::<T>::insert(
+			image_id,
+			ProgramInfo { kind: ProgramKind::Transfer, status: ProgramStatus::Active },
+		);
+
+		#[block]
+		{
+			Template::<T>::verify_receipt(image_id, receipt).expect("fixture receipts verify");
+		}
+	}
+
+	#[benchmark]
+	fn apply_transfers(a: Linear<2, 1_000>, q: Linear<0, 256>, t: Linear<1, 1_000>) {
+		fill_queue::<T>(q);
+		let journal = transfer_journal::<T>(a, t);
+
+		#[block]
+		{
+			TransferHandler::<T>::handle(&journal).expect("batch applies");
+		}
+
+		assert_eq!(BatchNumber::<T>::get(), 1);
+		assert!(TransferQueue::<T>::get().is_empty());
+	}
+
+	#[benchmark]
+	fn register_program() {
+		let origin = T::AdminOrigin::try_successful_origin().expect("admin origin is available");
+		let image_id = [1u32; 8];
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, image_id, ProgramKind::Transfer);
+
+		assert!(ProgramRegistry::<T>::contains_key(image_id));
+	}
+
+	#[benchmark]
+	fn deprecate_program() {
+		let origin = T::AdminOrigin::try_successful_origin().expect("admin origin is available");
+		let image_id = [1u32; 8];
+		ProgramRegistry::<T>::insert(
+			image_id,
+			ProgramInfo { kind: ProgramKind::Transfer, status: ProgramStatus::Active },
+		);
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, image_id);
+
+		assert_eq!(
+			ProgramRegistry::<T>::get(image_id).map(|program| program.status),
+			Some(ProgramStatus::Deprecated)
+		);
+	}
+
+	#[benchmark]
+	fn remove_program() {
+		let origin = T::AdminOrigin::try_successful_origin().expect("admin origin is available");
+		let image_id = [1u32; 8];
+		ProgramRegistry::<T>::insert(
+			image_id,
+			ProgramInfo { kind: ProgramKind::Transfer, status: ProgramStatus::Active },
+		);
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, image_id);
+
+		assert!(!ProgramRegistry::<T>::contains_key(image_id));
+	}
+
+	#[benchmark]
+	fn add_sequencer() {
+		let origin = T::AdminOrigin::try_successful_origin().expect("admin origin is available");
+		// Worst case, the account is checked against a set that is full but for one slot
+		let max = T::MaxSequencers::get();
+		let sequencers: Vec<T::AccountId> = (1..max).map(|i| account("sequencer", i, 0)).collect();
+		Sequencers::<T>::put(BoundedVec::truncate_from(sequencers));
+		let sequencer: T::AccountId = account("sequencer", 0, 0);
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, sequencer.clone());
+
+		assert!(Sequencers::<T>::get().contains(&sequencer));
+	}
+
+	#[benchmark]
+	fn remove_sequencer() {
+		let origin = T::AdminOrigin::try_successful_origin().expect("admin origin is available");
+		let max = T::MaxSequencers::get();
+		let sequencers: Vec<T::AccountId> = (0..max).map(|i| account("sequencer", i, 0)).collect();
+		Sequencers::<T>::put(BoundedVec::truncate_from(sequencers));
+		// Worst case, the last one is removed
+		let sequencer: T::AccountId = account("sequencer", max - 1, 0);
+
+		#[extrinsic_call]
+		_(origin as T::RuntimeOrigin, sequencer.clone());
+
+		assert!(!Sequencers::<T>::get().contains(&sequencer));
+	}
+
+	#[benchmark]
+	fn queue_transfer() {
+		fill_queue::<T>(T::MaxQueuedTransfers::get() - 1);
+		let sender: T::AccountId = whitelisted_caller();
+		T::Currency::make_free_balance_be(&sender, INITIAL_BALANCE.into());
+		let recipient: T::AccountId = account("recipient", 0, 0);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(sender), recipient, TRANSFER_AMOUNT);
+
+		assert_eq!(TransferQueue::<T>::decode_len(), Some(T::MaxQueuedTransfers::get() as usize));
+	}
+
+	#[benchmark]
+	fn deposit() {
+		fill_queue::<T>(T::MaxQueuedTransfers::get() - 1);
+		let caller: T::AccountId = whitelisted_caller();
+		T::Currency::make_free_balance_be(&caller, INITIAL_BALANCE.into());
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), TRANSFER_AMOUNT.into());
+
+		assert_eq!(TransferQueue::<T>::decode_len(), Some(T::MaxQueuedTransfers::get() as usize));
+	}
+
+	#[benchmark]
+	fn withdraw() {
+		fill_queue::<T>(T::MaxQueuedTransfers::get() - 1);
 		let caller: T::AccountId = whitelisted_caller();
-	}: _(RawOrigin::Signed(caller), s)
-	verify {
-		assert_eq!(Something::<T>::get(), Some(s));
+		T::Currency::make_free_balance_be(&caller, INITIAL_BALANCE.into());
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), TRANSFER_AMOUNT.into());
+
+		assert_eq!(TransferQueue::<T>::decode_len(), Some(T::MaxQueuedTransfers::get() as usize));
+	}
+
+	#[benchmark]
+	fn escape(p: Linear<0, 32>) {
+		fill_queue::<T>(T::MaxQueuedTransfers::get());
+		let caller: T::AccountId = whitelisted_caller();
+		let proof: Vec<_> = (0..p).map(|i| ([i as u8; 32], i % 2 == 0)).collect();
+		StateRoot::<T>::put(transfer_core::root_from_proof(
+			transfer_core::leaf(&account_id::<T>(&caller), TRANSFER_AMOUNT, 1),
+			&proof,
+		));
+		T::Currency::make_free_balance_be(&Template::<T>::bridge_account(), INITIAL_BALANCE.into());
+		frame_system::Pallet::<T>::set_block_number(T::EscapeTimeout::get());
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller.clone()), TRANSFER_AMOUNT, 1, proof);
+
+		assert!(Exited::<T>::contains_key(&caller));
 	}
 
 	impl_benchmark_test_suite!(Template, crate::mock::new_test_ext(), crate::mock::Test);
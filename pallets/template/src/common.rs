@@ -1,4 +1,102 @@
-/// The image id is unique per our program as it is a hash of some representation of the zkvm state.
-/// We can also obtain this from running the host code
-pub const TRANSFER_IMAGE_ID: [u32; 8] =
-	[457935672, 3918823227, 3023842824, 259760991, 4288050222, 1848304219, 1146781152, 3382653746];
+use codec::{Decode, Encode, MaxEncodedLen};
+use risc0_zkvm::{SegmentReceipt, SessionReceipt};
+use scale_info::TypeInfo;
+use sp_std::vec::Vec;
+
+/// The image id is unique per program, as it is a hash of some representation of the zkvm state.
+/// We can obtain it from running the host code
+pub type ImageId = [u32; 8];
+
+/// Which guest program an image id belongs to, and so how its journal is interpreted
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, Debug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(
+	feature = "std",
+	derive(frame_support::Serialize, frame_support::Deserialize),
+	serde(crate = "frame_support::serde")
+)]
+pub enum ProgramKind {
+	/// Batches of balance transfers, see `provers/transfer`
+	Transfer,
+	/// Programs handled by the runtime's own `ProgramHandler`s
+	Custom(u16),
+}
+
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, Debug, TypeInfo, MaxEncodedLen)]
+pub enum ProgramStatus {
+	/// Proofs of this program are accepted
+	Active,
+	/// Proofs of this program are no longer accepted, but the program is kept on record
+	Deprecated,
+}
+
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, Debug, TypeInfo, MaxEncodedLen)]
+pub struct ProgramInfo {
+	pub kind: ProgramKind,
+	pub status: ProgramStatus,
+}
+
+/// What a queued transfer moves funds between
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, Debug, TypeInfo, MaxEncodedLen)]
+pub enum QueuedKind {
+	/// From one rollup account to another
+	Transfer,
+	/// From funds locked on-chain to a rollup account
+	Deposit,
+	/// From a rollup account to an on-chain account, out of the locked funds
+	Withdrawal,
+}
+
+impl QueuedKind {
+	/// How the kind is committed in the queue hash
+	pub fn code(&self) -> u8 {
+		match self {
+			Self::Transfer => transfer_core::QUEUED_TRANSFER,
+			Self::Deposit => transfer_core::QUEUED_DEPOSIT,
+			Self::Withdrawal => transfer_core::QUEUED_WITHDRAWAL,
+		}
+	}
+}
+
+/// Why the guest left a transaction out of a batch
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, Debug, TypeInfo, MaxEncodedLen)]
+pub enum TransferRejection {
+	/// The sender could not pay the amount and the fee
+	InsufficientBalance,
+	/// The amount and the fee, or the recipient's new balance, do not fit in a balance
+	Overflow,
+	/// The transfer was not signed by its sender
+	BadSignature,
+	/// The transfer was not signed for the sender's next nonce
+	BadNonce,
+}
+
+/// An L2 transfer posted on-chain, which batches have to process within `ForcedInclusionDelay`
+/// blocks
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, TypeInfo, MaxEncodedLen)]
+pub struct QueuedTransfer<BlockNumber> {
+	pub kind: QueuedKind,
+	pub sender: [u8; 32],
+	pub recipient: [u8; 32],
+	pub amount: u128,
+	/// Block the transfer was queued at
+	pub queued_at: BlockNumber,
+}
+
+/// A `SessionReceipt` in a form SCALE can understand
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, TypeInfo)]
+pub struct Receipt {
+	/// Seal and index of each segment
+	pub segments: Vec<(Vec<u32>, u32)>,
+	pub journal: Vec<u8>,
+}
+
+impl From<Receipt> for SessionReceipt {
+	fn from(receipt: Receipt) -> Self {
+		let segments = receipt
+			.segments
+			.into_iter()
+			.map(|(seal, index)| SegmentReceipt { seal, index })
+			.collect();
+		SessionReceipt { segments, journal: receipt.journal }
+	}
+}
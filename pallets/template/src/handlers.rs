@@ -0,0 +1,64 @@
+use crate::{Config, ProgramKind};
+use frame_support::{dispatch::DispatchError, weights::Weight};
+use sp_std::vec::Vec;
+
+/// Interprets the journal of a verified receipt of one kind of program and applies its effects
+pub trait ProgramHandler<T: Config> {
+	/// The kind of program this handles, as registered in the program registry
+	const KIND: ProgramKind;
+
+	/// Upper bound on the weight of `handle` for this journal, charged before dispatch. Must be
+	/// cheap, as it runs before the receipt is verified
+	fn weight(journal: &[u8]) -> Weight;
+
+	/// Check that the journal can apply on top of the current state, without applying it. Used
+	/// to validate unsigned submissions in the transaction pool, so it should be cheap. Returns a
+	/// tag for the state the journal builds on, so that conflicting submissions replace each other
+	fn validate(journal: &[u8]) -> Result<Vec<u8>, DispatchError>;
+
+	/// Decode the journal and apply its effects, returning the weight actually used. The receipt
+	/// has already been verified against a registered image id of `KIND`
+	fn handle(journal: &[u8]) -> Result<Weight, DispatchError>;
+}
+
+/// The set of handlers the pallet dispatches verified receipts to, implemented for tuples of
+/// `ProgramHandler`s
+pub trait ProgramHandlers<T: Config> {
+	/// The highest weight any of the handlers could use for this journal
+	fn weight(journal: &[u8]) -> Weight;
+
+	/// Validate the journal with the handler for `kind`, or return `None` if there is none
+	fn validate(kind: ProgramKind, journal: &[u8]) -> Option<Result<Vec<u8>, DispatchError>>;
+
+	/// Hand the journal to the handler for `kind`, or return `None` if there is none
+	fn handle(kind: ProgramKind, journal: &[u8]) -> Option<Result<Weight, DispatchError>>;
+}
+
+#[impl_trait_for_tuples::impl_for_tuples(30)]
+impl<T: Config> ProgramHandlers<T> for Tuple {
+	for_tuples!( where #( Tuple: ProgramHandler<T> )* );
+
+	fn weight(journal: &[u8]) -> Weight {
+		let mut weight = Weight::zero();
+		for_tuples!( #( weight = weight.max(Tuple::weight(journal)); )* );
+		weight
+	}
+
+	fn validate(kind: ProgramKind, journal: &[u8]) -> Option<Result<Vec<u8>, DispatchError>> {
+		for_tuples!( #(
+			if Tuple::KIND == kind {
+				return Some(Tuple::validate(journal))
+			}
+		)* );
+		None
+	}
+
+	fn handle(kind: ProgramKind, journal: &[u8]) -> Option<Result<Weight, DispatchError>> {
+		for_tuples!( #(
+			if Tuple::KIND == kind {
+				return Some(Tuple::handle(journal))
+			}
+		)* );
+		None
+	}
+}
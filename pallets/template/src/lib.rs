@@ -15,13 +15,39 @@ mod tests;
 mod benchmarking;
 
 mod common;
+pub use common::{
+	ImageId, ProgramInfo, ProgramKind, ProgramStatus, QueuedKind, QueuedTransfer, Receipt,
+	TransferRejection,
+};
+
+mod handlers;
+pub use handlers::{ProgramHandler, ProgramHandlers};
+
+mod transfer;
+pub use transfer::TransferHandler;
+
+pub mod weights;
+pub use weights::WeightInfo;
+
+pub(crate) const LOG_TARGET: &str = "runtime::template";
 
 #[frame_support::pallet]
 pub mod pallet {
-	use crate::common::TRANSFER_IMAGE_ID;
-	use frame_support::{pallet_prelude::*, traits::Currency};
+	use crate::{
+		ImageId, ProgramHandlers, ProgramInfo, ProgramKind, ProgramStatus, QueuedKind,
+		QueuedTransfer, Receipt, TransferRejection, WeightInfo,
+	};
+	use frame_support::{
+		pallet_prelude::*,
+		sp_runtime::{
+			traits::{AccountIdConversion, One, Saturating, UniqueSaturatedInto, Zero},
+			Perbill,
+		},
+		traits::{Currency, ExistenceRequirement, ReservableCurrency},
+		PalletId,
+	};
 	use frame_system::pallet_prelude::*;
-	use risc0_zkvm::{serde::from_slice, sha::Digest, SegmentReceipt, SessionReceipt};
+	use risc0_zkvm::{sha::Digest, SessionReceipt};
 	use sp_std::vec::Vec;
 
 	#[pallet::pallet]
@@ -31,71 +57,682 @@ pub mod pallet {
 	pub type BalanceOf<T> =
 		<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
 
+	/// Merkle root of the rollup ledger after the last applied batch, see `transfer_core` for how
+	/// it is computed. The ledger starts out empty, with an all-zero root
+	#[pallet::storage]
+	#[pallet::getter(fn state_root)]
+	pub type StateRoot<T> = StorageValue<_, [u8; 32], ValueQuery>;
+
+	/// Number of the next batch expected, i.e. how many batches have been applied so far
+	#[pallet::storage]
+	#[pallet::getter(fn batch_number)]
+	pub type BatchNumber<T> = StorageValue<_, u64, ValueQuery>;
+
+	/// Guest programs whose proofs this pallet knows how to verify, by image id
+	#[pallet::storage]
+	#[pallet::getter(fn program_registry)]
+	pub type ProgramRegistry<T> = StorageMap<_, Blake2_128Concat, ImageId, ProgramInfo>;
+
+	/// Accounts allowed to submit proofs, taking turns by slot. Anyone may submit while it is empty
+	#[pallet::storage]
+	#[pallet::getter(fn sequencers)]
+	pub type Sequencers<T: Config> =
+		StorageValue<_, BoundedVec<T::AccountId, T::MaxSequencers>, ValueQuery>;
+
+	/// L2 transfers posted on-chain, oldest first, which batches have to process in order
+	#[pallet::storage]
+	#[pallet::getter(fn transfer_queue)]
+	pub type TransferQueue<T: Config> = StorageValue<
+		_,
+		BoundedVec<QueuedTransfer<T::BlockNumber>, T::MaxQueuedTransfers>,
+		ValueQuery,
+	>;
+
+	/// Block at which the last batch was applied
+	#[pallet::storage]
+	#[pallet::getter(fn last_batch_at)]
+	pub type LastBatchAt<T: Config> = StorageValue<_, T::BlockNumber, ValueQuery>;
+
+	/// Set once an account has escaped the rollup. The state root no longer matches the locked
+	/// funds after that, so no more batches are applied
+	#[pallet::storage]
+	#[pallet::getter(fn frozen)]
+	pub type Frozen<T> = StorageValue<_, bool, ValueQuery>;
+
+	/// Accounts which have escaped with their balance in the frozen state root
+	#[pallet::storage]
+	pub type Exited<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, ()>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config> {
+		/// Programs to register as active from genesis
+		pub programs: Vec<(ImageId, ProgramKind)>,
+		/// Initial sequencer set
+		pub sequencers: Vec<T::AccountId>,
+	}
+
+	#[cfg(feature = "std")]
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			Self { programs: Default::default(), sequencers: Default::default() }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+		fn build(&self) {
+			let sequencers: BoundedVec<_, _> =
+				self.sequencers.clone().try_into().expect("too many genesis sequencers");
+			Sequencers::<T>::put(sequencers);
+
+			for (image_id, kind) in &self.programs {
+				ProgramRegistry::<T>::insert(
+					image_id,
+					ProgramInfo { kind: *kind, status: ProgramStatus::Active },
+				);
+			}
+		}
+	}
+
 	/// Configure the pallet by specifying the parameters and types on which it depends.
 	#[pallet::config]
 	pub trait Config: frame_system::Config {
 		/// Because this pallet emits events, it depends on the runtime's definition of an event.
 		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
-		type Currency: Currency<<Self as frame_system::Config>::AccountId>;
+		type Currency: ReservableCurrency<<Self as frame_system::Config>::AccountId>;
+		/// Id of the account holding the funds deposited into the rollup
+		#[pallet::constant]
+		type PalletId: Get<PalletId>;
+		/// Origin allowed to manage the program registry
+		type AdminOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+		/// Handlers which apply the journals of verified receipts, by program kind
+		type ProgramHandlers: ProgramHandlers<Self>;
+		/// Weight information for extrinsics in this pallet.
+		type WeightInfo: WeightInfo;
+		/// Priority of unsigned proof submissions in the transaction pool
+		#[pallet::constant]
+		type UnsignedPriority: Get<TransactionPriority>;
+		/// Number of blocks an unsigned proof submission stays valid in the transaction pool
+		#[pallet::constant]
+		type UnsignedLongevity: Get<TransactionLongevity>;
+		/// L2 fee charged to the sender of every transfer in a batch
+		#[pallet::constant]
+		type TransferFee: Get<BalanceOf<Self>>;
+		/// Share of a batch's fees paid to the prover, the rest goes to `TreasuryAccount`
+		#[pallet::constant]
+		type ProverRewardShare: Get<Perbill>;
+		/// Account receiving the share of fees not paid to provers. Fee shares below the
+		/// existential deposit can only be paid into an existing account, so it has to be endowed
+		/// at genesis, as does `bridge_account`, which is kept alive so no locked funds are lost
+		/// as dust
+		#[pallet::constant]
+		type TreasuryAccount: Get<Self::AccountId>;
+		/// Maximum number of sequencers
+		#[pallet::constant]
+		type MaxSequencers: Get<u32>;
+		/// Number of blocks each sequencer has its turn for before the next one takes over
+		#[pallet::constant]
+		type SequencerSlotLength: Get<Self::BlockNumber>;
+		/// Number of blocks without a batch after which anyone may submit one, so the rollup
+		/// keeps going when sequencers are offline
+		#[pallet::constant]
+		type SequencerFallbackTimeout: Get<Self::BlockNumber>;
+		/// Maximum number of transfers waiting in the transfer queue
+		#[pallet::constant]
+		type MaxQueuedTransfers: Get<u32>;
+		/// Reserved from the sender of every queue entry until a batch processes it, so filling
+		/// the queue is not free
+		#[pallet::constant]
+		type QueueDeposit: Get<BalanceOf<Self>>;
+		/// Number of blocks after which a queued transfer has to be processed by the next batch
+		#[pallet::constant]
+		type ForcedInclusionDelay: Get<Self::BlockNumber>;
+		/// Number of blocks without a batch after which accounts may escape with their balance in
+		/// the last state root
+		#[pallet::constant]
+		type EscapeTimeout: Get<Self::BlockNumber>;
 	}
 	// Pallets use events to inform users when important changes are made.
 	// https://docs.substrate.io/main-docs/build/events-errors/
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
-		/// The seal was verified
-		VerificationSuccess,
+		/// The seal was verified and the journal applied
+		VerificationSuccess { image_id: ImageId },
+		/// A program was added to the registry
+		ProgramRegistered { image_id: ImageId, kind: ProgramKind },
+		/// A program no longer has its proofs accepted
+		ProgramDeprecated { image_id: ImageId },
+		/// A program was removed from the registry
+		ProgramRemoved { image_id: ImageId },
+		/// The fees of a batch were paid out to its prover and the treasury
+		FeesDistributed {
+			prover: T::AccountId,
+			prover_reward: BalanceOf<T>,
+			treasury_share: BalanceOf<T>,
+		},
+		/// An account was added to the sequencer set
+		SequencerAdded { sequencer: T::AccountId },
+		/// An account was removed from the sequencer set
+		SequencerRemoved { sequencer: T::AccountId },
+		/// An L2 transfer was added to the transfer queue
+		TransferQueued { sender: T::AccountId, recipient: T::AccountId, amount: u128 },
+		/// Funds were locked, and queued to be credited to the account on the rollup
+		Deposited { account: T::AccountId, amount: BalanceOf<T> },
+		/// A withdrawal from the account on the rollup was queued
+		WithdrawalQueued { account: T::AccountId, amount: BalanceOf<T> },
+		/// A withdrawal processed by a batch was paid out of the locked funds
+		WithdrawalPaid { account: T::AccountId, amount: BalanceOf<T> },
+		/// A batch was applied. Lists the new balance and nonce of every ledger account it
+		/// changed, so the ledger can be rebuilt from these events
+		LedgerUpdated {
+			batch_number: u64,
+			state_root: [u8; 32],
+			changes: Vec<([u8; 32], u128, u64)>,
+		},
+		/// A transaction of a batch was applied. Transactions are indexed in the order the batch
+		/// processed them, the queued ones first
+		TransferApplied { batch_number: u64, index: u32 },
+		/// A transaction of a batch was left out of it
+		TransferRejected { batch_number: u64, index: u32, reason: TransferRejection },
+		/// An account escaped the rollup, and was paid its balance in the last state root along
+		/// with its deposits still in the queue
+		Escaped { account: T::AccountId, amount: BalanceOf<T> },
 	}
 
 	#[pallet::error]
 	pub enum Error<T> {
 		/// The seal could not be verified
 		FailedVerification,
+		/// The journal is not in the shape the program's handler expects
+		JournalDecodeFailed,
+		/// An account id committed in the journal could not be decoded
+		InvalidAccountId,
+		/// The journal does not have exactly one new balance and nonce per changed account
+		AccountCountMismatch,
+		/// The batch has no transactions
+		EmptyBatch,
+		/// The batch was not built on the current state root
+		StateRootMismatch,
+		/// The batch is not the next one in sequence
+		BatchNumberMismatch,
+		/// The batch was proven with a different fee per transfer than `TransferFee`
+		FeeMismatch,
+		/// The image id is already in the registry
+		ProgramAlreadyRegistered,
+		/// The image id is not in the registry
+		UnknownProgram,
+		/// The program has been deprecated and its proofs are no longer accepted
+		ProgramNotActive,
+		/// No handler is configured for the kind of program the image id is registered as
+		NoHandler,
+		/// Only the sequencer assigned to the current slot may submit proofs
+		NotAssignedSequencer,
+		/// The account is already a sequencer
+		AlreadySequencer,
+		/// The account is not a sequencer
+		NotSequencer,
+		/// The sequencer set is full
+		TooManySequencers,
+		/// The transfer queue is full
+		QueueFull,
+		/// The batch did not process a prefix of the transfer queue
+		QueueMismatch,
+		/// The batch left out queued transfers older than `ForcedInclusionDelay`
+		QueuedTransferSkipped,
+		/// Queued amounts have to be at least the existential deposit, so withdrawals can always
+		/// be paid and dust does not fill the queue
+		AmountTooSmall,
+		/// Batches have been landing within `EscapeTimeout`, so there is no need to escape
+		EscapeNotOpen,
+		/// The proof does not lead from the balance of the account to the state root
+		InvalidInclusionProof,
+		/// The account has already escaped
+		AlreadyExited,
+		/// The account has nothing in the queue to escape with
+		NothingToEscape,
+		/// An account escaped, so the rollup no longer takes batches or queued transfers
+		RollupFrozen,
+		/// The batch's signatures were verified for another chain or rollup
+		DomainMismatch,
 	}
 
 	#[pallet::call]
-	impl<T: Config> Pallet<T>
-	where
-		BalanceOf<T>: From<u128>,
-	{
-		#[pallet::weight(1000000)]
+	impl<T: Config> Pallet<T> {
+		#[pallet::weight(
+			T::WeightInfo::verify_receipt(receipt.segments.len() as u32)
+				.saturating_add(T::ProgramHandlers::weight(&receipt.journal))
+				.saturating_add(T::DbWeight::get().reads_writes(2, 1))
+		)]
 		#[pallet::call_index(0)]
-		pub fn submit_transfer_proofs(
+		pub fn submit_proof(
+			origin: OriginFor<T>,
+			// Image id of the registered program the receipt was produced by
+			image_id: ImageId,
+			receipt: Receipt,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+			ensure!(Self::may_submit(Some(&who)), Error::<T>::NotAssignedSequencer);
+			Self::do_submit_proof(image_id, receipt)
+		}
+
+		#[pallet::weight(T::WeightInfo::register_program())]
+		#[pallet::call_index(1)]
+		pub fn register_program(
 			origin: OriginFor<T>,
-			// All accounts that were changed, in order
-			accounts: Vec<T::AccountId>,
-			substrate_segment_receipts: Vec<(Vec<u32>, u32)>,
-			// journal of (Vec<old balances>, Vec<new_balances>), both in order
-			journal: Vec<u8>,
+			image_id: ImageId,
+			kind: ProgramKind,
 		) -> DispatchResult {
-			// TODO: Look into whether there is a configuration where we don't need this extra
-			// signature check due to the other verifications i.e. add the receipt verification in
-			// the pallet validate unsigned portion
-			ensure_signed(origin)?;
-			let segments: Vec<SegmentReceipt> = substrate_segment_receipts
-				.into_iter()
-				.map(|(seal, index)| SegmentReceipt { seal, index })
-				.collect();
+			T::AdminOrigin::ensure_origin(origin)?;
+			ensure!(
+				!ProgramRegistry::<T>::contains_key(image_id),
+				Error::<T>::ProgramAlreadyRegistered
+			);
 
-			let receipt = SessionReceipt { segments, journal };
+			ProgramRegistry::<T>::insert(
+				image_id,
+				ProgramInfo { kind, status: ProgramStatus::Active },
+			);
 
-			receipt
-				.verify(Digest::new(TRANSFER_IMAGE_ID))
-				.map_err(|_| Error::<T>::FailedVerification)?;
+			Self::deposit_event(Event::<T>::ProgramRegistered { image_id, kind });
+			Ok(())
+		}
+
+		#[pallet::weight(T::WeightInfo::deprecate_program())]
+		#[pallet::call_index(2)]
+		pub fn deprecate_program(origin: OriginFor<T>, image_id: ImageId) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			ProgramRegistry::<T>::try_mutate(image_id, |program| {
+				let program = program.as_mut().ok_or(Error::<T>::UnknownProgram)?;
+				program.status = ProgramStatus::Deprecated;
+				Ok::<_, Error<T>>(())
+			})?;
+
+			Self::deposit_event(Event::<T>::ProgramDeprecated { image_id });
+			Ok(())
+		}
+
+		#[pallet::weight(T::WeightInfo::remove_program())]
+		#[pallet::call_index(3)]
+		pub fn remove_program(origin: OriginFor<T>, image_id: ImageId) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+			ensure!(ProgramRegistry::<T>::contains_key(image_id), Error::<T>::UnknownProgram);
+
+			ProgramRegistry::<T>::remove(image_id);
+
+			Self::deposit_event(Event::<T>::ProgramRemoved { image_id });
+			Ok(())
+		}
+
+		/// Same as `submit_proof`, but without a signature, so provers don't need funded
+		/// accounts. The receipt is verified in `validate_unsigned` before it enters the pool
+		#[pallet::weight(
+			T::WeightInfo::verify_receipt(receipt.segments.len() as u32)
+				.saturating_add(T::ProgramHandlers::weight(&receipt.journal))
+				.saturating_add(T::DbWeight::get().reads_writes(2, 1))
+		)]
+		#[pallet::call_index(4)]
+		pub fn submit_proof_unsigned(
+			origin: OriginFor<T>,
+			// Image id of the registered program the receipt was produced by
+			image_id: ImageId,
+			receipt: Receipt,
+		) -> DispatchResultWithPostInfo {
+			ensure_none(origin)?;
+			ensure!(Self::may_submit(None), Error::<T>::NotAssignedSequencer);
+			Self::do_submit_proof(image_id, receipt)
+		}
+
+		#[pallet::weight(T::WeightInfo::add_sequencer())]
+		#[pallet::call_index(5)]
+		pub fn add_sequencer(origin: OriginFor<T>, sequencer: T::AccountId) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			Sequencers::<T>::try_mutate(|sequencers| {
+				ensure!(!sequencers.contains(&sequencer), Error::<T>::AlreadySequencer);
+				sequencers
+					.try_push(sequencer.clone())
+					.map_err(|_| Error::<T>::TooManySequencers)
+			})?;
+
+			Self::deposit_event(Event::<T>::SequencerAdded { sequencer });
+			Ok(())
+		}
+
+		#[pallet::weight(T::WeightInfo::remove_sequencer())]
+		#[pallet::call_index(6)]
+		pub fn remove_sequencer(origin: OriginFor<T>, sequencer: T::AccountId) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			Sequencers::<T>::try_mutate(|sequencers| {
+				let index = sequencers
+					.iter()
+					.position(|account| *account == sequencer)
+					.ok_or(Error::<T>::NotSequencer)?;
+				// Keep the order, so the remaining sequencers keep their turns
+				sequencers.remove(index);
+				Ok::<_, Error<T>>(())
+			})?;
+
+			Self::deposit_event(Event::<T>::SequencerRemoved { sequencer });
+			Ok(())
+		}
+
+		/// Post an L2 transfer from the caller's account on-chain, so it gets into a batch
+		/// without relying on any prover picking it up
+		#[pallet::weight(T::WeightInfo::queue_transfer())]
+		#[pallet::call_index(7)]
+		pub fn queue_transfer(
+			origin: OriginFor<T>,
+			recipient: T::AccountId,
+			amount: u128,
+		) -> DispatchResult {
+			let sender = ensure_signed(origin)?;
+
+			Self::enqueue(QueuedKind::Transfer, &sender, &recipient, amount)?;
+
+			Self::deposit_event(Event::<T>::TransferQueued { sender, recipient, amount });
+			Ok(())
+		}
+
+		/// Lock funds of the caller in the pallet, to be credited to the caller's rollup account
+		/// by the next batch
+		#[pallet::weight(T::WeightInfo::deposit())]
+		#[pallet::call_index(8)]
+		pub fn deposit(origin: OriginFor<T>, amount: BalanceOf<T>) -> DispatchResult {
+			let account = ensure_signed(origin)?;
+
+			Self::enqueue(QueuedKind::Deposit, &account, &account, amount.unique_saturated_into())?;
+			T::Currency::transfer(
+				&account,
+				&Self::bridge_account(),
+				amount,
+				ExistenceRequirement::AllowDeath,
+			)?;
+
+			Self::deposit_event(Event::<T>::Deposited { account, amount });
+			Ok(())
+		}
+
+		/// Queue a withdrawal from the caller's rollup account. Once a batch processes it, the
+		/// amount is paid out of the locked funds
+		#[pallet::weight(T::WeightInfo::withdraw())]
+		#[pallet::call_index(9)]
+		pub fn withdraw(origin: OriginFor<T>, amount: BalanceOf<T>) -> DispatchResult {
+			let account = ensure_signed(origin)?;
+
+			Self::enqueue(
+				QueuedKind::Withdrawal,
+				&account,
+				&account,
+				amount.unique_saturated_into(),
+			)?;
+
+			Self::deposit_event(Event::<T>::WithdrawalQueued { account, amount });
+			Ok(())
+		}
+
+		/// Exit with the caller's `balance` in the ledger, proven along with its `nonce` by a
+		/// Merkle `proof` against the current state root, once no batch has been applied for
+		/// `EscapeTimeout` blocks. The first escape freezes the rollup
+		#[pallet::weight(T::WeightInfo::escape(proof.len() as u32))]
+		#[pallet::call_index(10)]
+		pub fn escape(
+			origin: OriginFor<T>,
+			balance: u128,
+			nonce: u64,
+			proof: Vec<([u8; 32], bool)>,
+		) -> DispatchResult {
+			let account = ensure_signed(origin)?;
+			Self::ensure_escape_open(&account)?;
+
+			let id = Self::account_bytes(&account)?;
+			ensure!(
+				transfer_core::verify_proof(
+					transfer_core::leaf(&id, balance, nonce),
+					&proof,
+					&StateRoot::<T>::get()
+				),
+				Error::<T>::InvalidInclusionProof
+			);
 
-			// sender original, sender final, recipient original, recipient final
-			let (_, balances): (Vec<[u8; 16]>, Vec<[u8; 16]>) = from_slice(&receipt.journal).expect(
-				"Journal output should deserialize into the same types (& order) that it was written",
+			// Deposits still in the queue were never credited in the ledger, so they are returned
+			// along with the balance
+			let amount = balance.saturating_add(Self::pending_deposits(&id));
+			Self::exit(account, amount.unique_saturated_into())
+		}
+
+		/// Exit with only the caller's deposits still in the queue, for an account the provers
+		/// never credited and so has no balance in the ledger to prove. Like `escape`, it is only
+		/// open after `EscapeTimeout` blocks without a batch, freezes the rollup, and can be done
+		/// once, so any balance the account does have in the ledger is given up
+		#[pallet::weight(T::WeightInfo::escape(0))]
+		#[pallet::call_index(11)]
+		pub fn escape_deposits(origin: OriginFor<T>) -> DispatchResult {
+			let account = ensure_signed(origin)?;
+			Self::ensure_escape_open(&account)?;
+
+			let id = Self::account_bytes(&account)?;
+			// Otherwise any account could freeze the rollup for free
+			ensure!(
+				TransferQueue::<T>::get().iter().any(|queued| queued.sender == id),
+				Error::<T>::NothingToEscape
 			);
+			Self::exit(account, Self::pending_deposits(&id).unique_saturated_into())
+		}
+	}
+
+	#[pallet::validate_unsigned]
+	impl<T: Config> ValidateUnsigned for Pallet<T> {
+		type Call = Call<T>;
+
+		fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+			let Call::submit_proof_unsigned { image_id, receipt } = call else {
+				return InvalidTransaction::Call.into()
+			};
+			// Unsigned submissions can't be attributed to a sequencer
+			if !Self::may_submit(None) {
+				return InvalidTransaction::Call.into()
+			}
+
+			// Stale and malformed batches are turned away before the seal, which is by far the most
+			// expensive check, is verified
+			let program = ProgramRegistry::<T>::get(image_id)
+				.filter(|program| program.status == ProgramStatus::Active)
+				.ok_or(InvalidTransaction::Call)?;
+			let tag = Self::validate_journal(program.kind, &receipt.journal)?;
+			Self::verify_receipt(*image_id, receipt.clone()).map_err(|error| {
+				if error == Error::<T>::FailedVerification.into() {
+					InvalidTransaction::BadProof
+				} else {
+					InvalidTransaction::Call
+				}
+			})?;
 
-			accounts.into_iter().zip(balances.into_iter()).for_each(|(account, balance)| {
-				let balance = u128::from_be_bytes(balance);
-				// TODO: Check if there is a broader way to set new state
-				T::Currency::make_free_balance_be(&account, balance.into());
-			});
+			ValidTransaction::with_tag_prefix("TemplateModule")
+				.priority(T::UnsignedPriority::get())
+				.and_provides((program.kind, tag))
+				.longevity(T::UnsignedLongevity::get())
+				.propagate(true)
+				.build()
+		}
+
+		// The seal is verified again during dispatch, so only the cheap checks are repeated here
+		fn pre_dispatch(call: &Self::Call) -> Result<(), TransactionValidityError> {
+			let Call::submit_proof_unsigned { image_id, receipt } = call else {
+				return Err(InvalidTransaction::Call.into())
+			};
+			if !Self::may_submit(None) {
+				return Err(InvalidTransaction::Call.into())
+			}
 
-			Self::deposit_event(Event::<T>::VerificationSuccess);
+			let program = ProgramRegistry::<T>::get(image_id)
+				.filter(|program| program.status == ProgramStatus::Active)
+				.ok_or(InvalidTransaction::Call)?;
+			Self::validate_journal(program.kind, &receipt.journal)?;
 			Ok(())
 		}
 	}
+
+	impl<T: Config> Pallet<T> {
+		fn do_submit_proof(image_id: ImageId, receipt: Receipt) -> DispatchResultWithPostInfo {
+			let verify_weight = T::WeightInfo::verify_receipt(receipt.segments.len() as u32);
+			let (program, receipt) = Self::verify_receipt(image_id, receipt)?;
+
+			let handle_weight = T::ProgramHandlers::handle(program.kind, &receipt.journal)
+				.ok_or(Error::<T>::NoHandler)??;
+			LastBatchAt::<T>::put(frame_system::Pallet::<T>::block_number());
+
+			Self::deposit_event(Event::<T>::VerificationSuccess { image_id });
+			// Along with the reads of `Sequencers` and `LastBatchAt` deciding who may submit
+			Ok(Some(
+				verify_weight
+					.saturating_add(handle_weight)
+					.saturating_add(T::DbWeight::get().reads_writes(2, 1)),
+			)
+			.into())
+		}
+
+		/// Account holding the funds locked in the rollup
+		pub fn bridge_account() -> T::AccountId {
+			T::PalletId::get().into_account_truncating()
+		}
+
+		fn enqueue(
+			kind: QueuedKind,
+			sender: &T::AccountId,
+			recipient: &T::AccountId,
+			amount: u128,
+		) -> DispatchResult {
+			ensure!(!Frozen::<T>::get(), Error::<T>::RollupFrozen);
+			ensure!(
+				amount >= T::Currency::minimum_balance().unique_saturated_into(),
+				Error::<T>::AmountTooSmall
+			);
+			T::Currency::reserve(sender, T::QueueDeposit::get())?;
+			let transfer = QueuedTransfer {
+				kind,
+				sender: Self::account_bytes(sender)?,
+				recipient: Self::account_bytes(recipient)?,
+				amount,
+				queued_at: frame_system::Pallet::<T>::block_number(),
+			};
+			TransferQueue::<T>::try_append(transfer).map_err(|_| Error::<T>::QueueFull.into())
+		}
+
+		/// The 32 bytes an account is committed as in journals
+		fn account_bytes(account: &T::AccountId) -> Result<[u8; 32], DispatchError> {
+			account.encode().try_into().map_err(|_| Error::<T>::InvalidAccountId.into())
+		}
+
+		fn ensure_escape_open(account: &T::AccountId) -> DispatchResult {
+			let now = frame_system::Pallet::<T>::block_number();
+			ensure!(
+				Frozen::<T>::get() ||
+					now >= LastBatchAt::<T>::get().saturating_add(T::EscapeTimeout::get()),
+				Error::<T>::EscapeNotOpen
+			);
+			ensure!(!Exited::<T>::contains_key(account), Error::<T>::AlreadyExited);
+			Ok(())
+		}
+
+		/// Total of the account's deposits still in the queue
+		fn pending_deposits(id: &[u8; 32]) -> u128 {
+			TransferQueue::<T>::get()
+				.iter()
+				.filter(|queued| queued.kind == QueuedKind::Deposit && &queued.recipient == id)
+				.fold(0u128, |pending, queued| pending.saturating_add(queued.amount))
+		}
+
+		/// Pay out an escape from the locked funds and freeze the rollup, as the state root no
+		/// longer matches them. No batch will process the account's queue entries after that, so
+		/// their deposits are returned too
+		fn exit(account: T::AccountId, amount: BalanceOf<T>) -> DispatchResult {
+			let id = Self::account_bytes(&account)?;
+			let entries =
+				TransferQueue::<T>::get().iter().filter(|queued| queued.sender == id).count();
+			T::Currency::unreserve(
+				&account,
+				T::QueueDeposit::get().saturating_mul((entries as u32).into()),
+			);
+
+			Exited::<T>::insert(&account, ());
+			Frozen::<T>::put(true);
+			if !amount.is_zero() {
+				T::Currency::transfer(
+					&Self::bridge_account(),
+					&account,
+					amount,
+					ExistenceRequirement::KeepAlive,
+				)?;
+			}
+
+			Self::deposit_event(Event::<T>::Escaped { account, amount });
+			Ok(())
+		}
+
+		/// The sequencer whose turn it is at the current block, if there are any
+		pub fn assigned_sequencer() -> Option<T::AccountId> {
+			let sequencers = Sequencers::<T>::get();
+			if sequencers.is_empty() {
+				return None
+			}
+
+			let now = frame_system::Pallet::<T>::block_number();
+			let slot: u64 =
+				(now / T::SequencerSlotLength::get().max(One::one())).unique_saturated_into();
+			sequencers.get((slot % sequencers.len() as u64) as usize).cloned()
+		}
+
+		/// Whether `submitter` may submit a proof now, where `None` stands for an unsigned
+		/// submission. Anyone may submit while there are no sequencers, or once no batch was
+		/// applied for `SequencerFallbackTimeout` blocks
+		pub(crate) fn may_submit(submitter: Option<&T::AccountId>) -> bool {
+			let Some(assigned) = Self::assigned_sequencer() else { return true };
+
+			let now = frame_system::Pallet::<T>::block_number();
+			if now >= LastBatchAt::<T>::get().saturating_add(T::SequencerFallbackTimeout::get()) {
+				return true
+			}
+
+			submitter == Some(&assigned)
+		}
+
+		/// Check the journal applies on top of the current state, returning the handler's tag
+		fn validate_journal(
+			kind: ProgramKind,
+			journal: &[u8],
+		) -> Result<Vec<u8>, TransactionValidityError> {
+			T::ProgramHandlers::validate(kind, journal)
+				.ok_or(InvalidTransaction::Call)?
+				.map_err(|error| {
+					if error == Error::<T>::StateRootMismatch.into() ||
+						error == Error::<T>::BatchNumberMismatch.into()
+					{
+						InvalidTransaction::Stale.into()
+					} else {
+						InvalidTransaction::Call.into()
+					}
+				})
+		}
+
+		/// Check the image id is registered and active, and verify the receipt against it
+		pub(crate) fn verify_receipt(
+			image_id: ImageId,
+			receipt: Receipt,
+		) -> Result<(ProgramInfo, SessionReceipt), DispatchError> {
+			let program = ProgramRegistry::<T>::get(image_id).ok_or(Error::<T>::UnknownProgram)?;
+			ensure!(program.status == ProgramStatus::Active, Error::<T>::ProgramNotActive);
+			ensure!(!receipt.segments.is_empty(), Error::<T>::FailedVerification);
+
+			let receipt: SessionReceipt = receipt.into();
+
+			receipt
+				.verify(Digest::new(image_id))
+				.map_err(|_| Error::<T>::FailedVerification)?;
+
+			Ok((program, receipt))
+		}
+	}
 }
@@ -1,9 +1,18 @@
-use crate as pallet_template;
-use frame_support::traits::{ConstU16, ConstU64};
+use crate::{
+	self as pallet_template, transfer::TransferJournal, ImageId, ProgramKind, Receipt, StateRoot,
+};
+use codec::Decode;
+use frame_support::{
+	parameter_types,
+	traits::{ConstU128, ConstU16, ConstU32, ConstU64, GenesisBuild},
+	PalletId,
+};
+use risc0_zkvm::serde::from_slice;
 use sp_core::H256;
 use sp_runtime::{
 	testing::Header,
-	traits::{BlakeTwo256, IdentityLookup},
+	traits::{AccountIdConversion, BlakeTwo256, IdentityLookup},
+	AccountId32, Perbill,
 };
 
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
@@ -17,6 +26,7 @@ frame_support::construct_runtime!(
 		UncheckedExtrinsic = UncheckedExtrinsic,
 	{
 		System: frame_system,
+		Balances: pallet_balances,
 		TemplateModule: pallet_template,
 	}
 );
@@ -32,27 +42,112 @@ impl frame_system::Config for Test {
 	type BlockNumber = u64;
 	type Hash = H256;
 	type Hashing = BlakeTwo256;
-	type AccountId = u64;
+	type AccountId = AccountId32;
 	type Lookup = IdentityLookup<Self::AccountId>;
 	type Header = Header;
 	type RuntimeEvent = RuntimeEvent;
 	type BlockHashCount = ConstU64<250>;
 	type Version = ();
 	type PalletInfo = PalletInfo;
-	type AccountData = ();
+	type AccountData = pallet_balances::AccountData<u128>;
 	type OnNewAccount = ();
 	type OnKilledAccount = ();
 	type SystemWeightInfo = ();
 	type SS58Prefix = ConstU16<42>;
 	type OnSetCode = ();
-	type MaxConsumers = frame_support::traits::ConstU32<16>;
+	type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+	type MaxLocks = ConstU32<50>;
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+	type Balance = u128;
+	type RuntimeEvent = RuntimeEvent;
+	type DustRemoval = ();
+	type ExistentialDeposit = ConstU128<1>;
+	type AccountStore = System;
+	type WeightInfo = ();
 }
 
 impl pallet_template::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
+	type Currency = Balances;
+	type PalletId = RollupPalletId;
+	type AdminOrigin = frame_system::EnsureRoot<AccountId32>;
+	type ProgramHandlers = (pallet_template::TransferHandler<Test>,);
+	type WeightInfo = ();
+	type UnsignedPriority = ConstU64<{ u64::MAX / 2 }>;
+	type UnsignedLongevity = ConstU64<100>;
+	type TransferFee = ConstU128<FIXTURE_FEE>;
+	type ProverRewardShare = ProverRewardShare;
+	type TreasuryAccount = TreasuryAccount;
+	type MaxSequencers = ConstU32<4>;
+	type SequencerSlotLength = ConstU64<10>;
+	type SequencerFallbackTimeout = ConstU64<50>;
+	type MaxQueuedTransfers = ConstU32<4>;
+	type QueueDeposit = ConstU128<QUEUE_DEPOSIT>;
+	type ForcedInclusionDelay = ConstU64<20>;
+	type EscapeTimeout = ConstU64<100>;
+}
+
+parameter_types! {
+	pub const RollupPalletId: PalletId = PalletId(*b"py/rolup");
+	pub const ProverRewardShare: Perbill = Perbill::from_percent(80);
+	pub const TreasuryAccount: AccountId32 = AccountId32::new([0xee; 32]);
+}
+
+/// Balance of every ledger account in the ledger the fixtures were proven against
+pub const FIXTURE_BALANCE: u128 = 1 << 60;
+
+/// Account funded on the relay chain side, to deposit from
+pub const DEPOSITOR: [u8; 32] = [0xff; 32];
+
+/// Fee per transfer the fixtures were proven with
+pub const FIXTURE_FEE: u128 = 1_000;
+
+/// Reserved from the sender of every queue entry
+pub const QUEUE_DEPOSIT: u128 = 5;
+
+/// A receipt of the transfer guest with a single segment, see `fixtures/README.md`
+pub fn fixture() -> (ImageId, Receipt) {
+	let mut bytes: &[u8] = include_bytes!("../fixtures/transfer_1_segment.scale");
+	Decode::decode(&mut bytes).expect("fixtures are SCALE encoded (image id, receipt) pairs")
+}
+
+pub fn fixture_journal() -> TransferJournal {
+	from_slice(&fixture().1.journal).expect("fixture journal is a transfer journal")
+}
+
+pub fn bridge_account() -> AccountId32 {
+	RollupPalletId::get().into_account_truncating()
 }
 
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
-	frame_system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
+	let mut storage = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+
+	// The locked funds back the fixture ledger, which holds `FIXTURE_BALANCE` per account. The
+	// treasury has to exist to be paid fee shares below the existential deposit
+	let (accounts, ..) = fixture_journal();
+	pallet_balances::GenesisConfig::<Test> {
+		balances: vec![
+			(bridge_account(), FIXTURE_BALANCE * accounts.len() as u128),
+			(TreasuryAccount::get(), 1),
+			(AccountId32::new(DEPOSITOR), FIXTURE_BALANCE),
+		],
+	}
+	.assimilate_storage(&mut storage)
+	.unwrap();
+
+	pallet_template::GenesisConfig::<Test> {
+		programs: vec![(fixture().0, ProgramKind::Transfer)],
+		sequencers: vec![],
+	}
+	.assimilate_storage(&mut storage)
+	.unwrap();
+
+	let mut ext: sp_io::TestExternalities = storage.into();
+	ext.execute_with(|| StateRoot::<Test>::put(fixture_journal().3));
+	ext
 }
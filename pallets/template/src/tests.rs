@@ -1,27 +1,996 @@
-use crate::{mock::*, Error, Event};
-use frame_support::{assert_noop, assert_ok};
+use crate::{
+	mock::*,
+	transfer::{encode_journal, queue_hash},
+	BatchNumber, Call, Error, Event, LastBatchAt, ProgramHandler, ProgramKind, ProgramRegistry,
+	ProgramStatus, QueuedKind, QueuedTransfer, StateRoot, TransferHandler, TransferRejection,
+};
+use frame_support::{
+	assert_noop, assert_ok,
+	traits::{Currency, Get},
+};
+use sp_core::H256;
+use sp_runtime::{
+	traits::ValidateUnsigned,
+	transaction_validity::{InvalidTransaction, TransactionSource},
+	AccountId32, DispatchError,
+};
+use transfer_core::{
+	TRANSFER_APPLIED, TRANSFER_BAD_NONCE, TRANSFER_BAD_SIGNATURE, TRANSFER_INSUFFICIENT_BALANCE,
+	TRANSFER_OVERFLOW,
+};
+
+fn signer() -> RuntimeOrigin {
+	RuntimeOrigin::signed(AccountId32::new(DEPOSITOR))
+}
+
+/// Journal of the next batch, changing the balances and nonces of the ledger accounts in
+/// `changes`, collecting `fees`, processing the first `processed` queued transfers with the given
+/// hash of them, paying out `exits` and with the `outcomes` of its transactions
+fn batch_journal(
+	changes: Vec<([u8; 32], u128, u64)>,
+	fees: u128,
+	processed: u64,
+	hash: [u8; 32],
+	exits: Vec<([u8; 32], u128)>,
+	outcomes: Vec<u8>,
+) -> Vec<u8> {
+	let (accounts, (new_balances, new_nonces)) = changes
+		.into_iter()
+		.map(|(account, balance, nonce)| (account, (balance.to_be_bytes(), nonce)))
+		.unzip();
+	encode_journal(&(
+		accounts,
+		new_balances,
+		new_nonces,
+		StateRoot::<Test>::get(),
+		[1; 32],
+		BatchNumber::<Test>::get(),
+		[0xaa; 32],
+		FIXTURE_FEE.to_be_bytes(),
+		fees.to_be_bytes(),
+		processed,
+		hash,
+		exits
+			.into_iter()
+			.map(|(account, amount)| (account, amount.to_be_bytes()))
+			.collect(),
+		outcomes,
+		System::block_hash(0).0,
+		RollupPalletId::get().0,
+	))
+}
 
 #[test]
-fn it_works_for_default_value() {
+fn applies_valid_batch() {
 	new_test_ext().execute_with(|| {
 		// Go past genesis block so events get deposited
 		System::set_block_number(1);
-		// Dispatch a signed extrinsic.
-		assert_ok!(TemplateModule::do_something(RuntimeOrigin::signed(1), 42));
-		// Read pallet storage and assert an expected result.
-		assert_eq!(TemplateModule::something(), Some(42));
-		// Assert that the correct event was deposited
-		System::assert_last_event(Event::SomethingStored { something: 42, who: 1 }.into());
+		let (image_id, receipt) = fixture();
+		let (accounts, new_balances, new_nonces, _, new_root, _, prover, _, fees, ..) =
+			fixture_journal();
+		let locked = Balances::free_balance(bridge_account());
+
+		assert_ok!(TemplateModule::submit_proof(signer(), image_id, receipt));
+
+		assert_eq!(StateRoot::<Test>::get(), new_root);
+		assert_eq!(BatchNumber::<Test>::get(), 1);
+		let fees = u128::from_be_bytes(fees);
+		assert_eq!(Balances::free_balance(bridge_account()), locked - fees);
+		System::assert_has_event(
+			Event::FeesDistributed {
+				prover: AccountId32::new(prover),
+				prover_reward: fees * 4 / 5,
+				treasury_share: fees - fees * 4 / 5,
+			}
+			.into(),
+		);
+		System::assert_has_event(
+			Event::LedgerUpdated {
+				batch_number: 0,
+				state_root: new_root,
+				changes: accounts
+					.into_iter()
+					.zip(new_balances.into_iter().zip(new_nonces))
+					.map(|(account, (balance, nonce))| {
+						(account, u128::from_be_bytes(balance), nonce)
+					})
+					.collect(),
+			}
+			.into(),
+		);
+		System::assert_last_event(Event::VerificationSuccess { image_id }.into());
+	});
+}
+
+#[test]
+fn rejects_replayed_batch() {
+	new_test_ext().execute_with(|| {
+		let (image_id, receipt) = fixture();
+
+		assert_ok!(TemplateModule::submit_proof(signer(), image_id, receipt.clone()));
+		assert_noop!(
+			TemplateModule::submit_proof(signer(), image_id, receipt),
+			Error::<Test>::StateRootMismatch
+		);
+	});
+}
+
+#[test]
+fn rejects_unregistered_image_id() {
+	new_test_ext().execute_with(|| {
+		let (_, receipt) = fixture();
+
+		assert_noop!(
+			TemplateModule::submit_proof(signer(), [0u32; 8], receipt),
+			Error::<Test>::UnknownProgram
+		);
+	});
+}
+
+#[test]
+fn rejects_receipt_of_another_image_id() {
+	new_test_ext().execute_with(|| {
+		let (_, receipt) = fixture();
+		assert_ok!(TemplateModule::register_program(
+			RuntimeOrigin::root(),
+			[0u32; 8],
+			ProgramKind::Transfer
+		));
+
+		assert_noop!(
+			TemplateModule::submit_proof(signer(), [0u32; 8], receipt),
+			Error::<Test>::FailedVerification
+		);
+	});
+}
+
+#[test]
+fn rejects_tampered_seal() {
+	new_test_ext().execute_with(|| {
+		let (image_id, mut receipt) = fixture();
+		receipt.segments[0].0[0] ^= 1;
+
+		assert_noop!(
+			TemplateModule::submit_proof(signer(), image_id, receipt),
+			Error::<Test>::FailedVerification
+		);
+	});
+}
+
+#[test]
+fn rejects_tampered_journal() {
+	new_test_ext().execute_with(|| {
+		let (image_id, mut receipt) = fixture();
+		let last = receipt.journal.len() - 1;
+		receipt.journal[last] ^= 1;
+
+		assert_noop!(
+			TemplateModule::submit_proof(signer(), image_id, receipt),
+			Error::<Test>::FailedVerification
+		);
+	});
+}
+
+#[test]
+fn rejects_deprecated_program() {
+	new_test_ext().execute_with(|| {
+		let (image_id, receipt) = fixture();
+		assert_ok!(TemplateModule::deprecate_program(RuntimeOrigin::root(), image_id));
+
+		assert_eq!(
+			ProgramRegistry::<Test>::get(image_id).map(|program| program.status),
+			Some(ProgramStatus::Deprecated)
+		);
+		assert_noop!(
+			TemplateModule::submit_proof(signer(), image_id, receipt),
+			Error::<Test>::ProgramNotActive
+		);
+	});
+}
+
+#[test]
+fn only_admin_manages_registry() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			TemplateModule::register_program(signer(), [0u32; 8], ProgramKind::Transfer),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			TemplateModule::remove_program(signer(), fixture().0),
+			DispatchError::BadOrigin
+		);
+	});
+}
+
+#[test]
+fn applies_unsigned_batch() {
+	new_test_ext().execute_with(|| {
+		let (image_id, receipt) = fixture();
+		let call = Call::submit_proof_unsigned { image_id, receipt: receipt.clone() };
+
+		let valid = TemplateModule::validate_unsigned(TransactionSource::External, &call).unwrap();
+		assert_eq!(valid.priority, u64::MAX / 2);
+		assert_eq!(valid.longevity, 100);
+		assert_ok!(TemplateModule::pre_dispatch(&call));
+
+		assert_noop!(
+			TemplateModule::submit_proof_unsigned(signer(), image_id, receipt.clone()),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(TemplateModule::submit_proof_unsigned(RuntimeOrigin::none(), image_id, receipt));
+		assert_eq!(BatchNumber::<Test>::get(), 1);
+
+		// Once the batch is applied, the same submission is stale
+		assert_eq!(
+			TemplateModule::validate_unsigned(TransactionSource::External, &call),
+			Err(InvalidTransaction::Stale.into())
+		);
+		assert_eq!(TemplateModule::pre_dispatch(&call), Err(InvalidTransaction::Stale.into()));
+
+		// Stale submissions are turned away before their seal is verified
+		let Call::submit_proof_unsigned { mut receipt, .. } = call else { unreachable!() };
+		receipt.segments[0].0[0] ^= 1;
+		assert_eq!(
+			TemplateModule::validate_unsigned(
+				TransactionSource::External,
+				&Call::submit_proof_unsigned { image_id, receipt }
+			),
+			Err(InvalidTransaction::Stale.into())
+		);
+	});
+}
+
+#[test]
+fn rejects_invalid_unsigned_submissions() {
+	new_test_ext().execute_with(|| {
+		let (image_id, receipt) = fixture();
+
+		let unknown = Call::submit_proof_unsigned { image_id: [0u32; 8], receipt: receipt.clone() };
+		assert_eq!(
+			TemplateModule::validate_unsigned(TransactionSource::External, &unknown),
+			Err(InvalidTransaction::Call.into())
+		);
+
+		let mut tampered = receipt;
+		tampered.segments[0].0[0] ^= 1;
+		let tampered = Call::submit_proof_unsigned { image_id, receipt: tampered };
+		assert_eq!(
+			TemplateModule::validate_unsigned(TransactionSource::External, &tampered),
+			Err(InvalidTransaction::BadProof.into())
+		);
+
+		let signed = Call::register_program { image_id, kind: ProgramKind::Transfer };
+		assert_eq!(
+			TemplateModule::validate_unsigned(TransactionSource::External, &signed),
+			Err(InvalidTransaction::Call.into())
+		);
+	});
+}
+
+fn sequencer(id: u8) -> AccountId32 {
+	AccountId32::new([id; 32])
+}
+
+#[test]
+fn rotates_sequencers_by_slot() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let (image_id, receipt) = fixture();
+		assert_ok!(TemplateModule::add_sequencer(RuntimeOrigin::root(), sequencer(0xa1)));
+		assert_ok!(TemplateModule::add_sequencer(RuntimeOrigin::root(), sequencer(0xb1)));
+
+		assert_eq!(TemplateModule::assigned_sequencer(), Some(sequencer(0xa1)));
+		assert_noop!(
+			TemplateModule::submit_proof(
+				RuntimeOrigin::signed(sequencer(0xb1)),
+				image_id,
+				receipt.clone()
+			),
+			Error::<Test>::NotAssignedSequencer
+		);
+		assert_ok!(TemplateModule::submit_proof(
+			RuntimeOrigin::signed(sequencer(0xa1)),
+			image_id,
+			receipt
+		));
+
+		// Slots are `SequencerSlotLength` blocks long, and wrap around the set
+		System::set_block_number(10);
+		assert_eq!(TemplateModule::assigned_sequencer(), Some(sequencer(0xb1)));
+		System::set_block_number(20);
+		assert_eq!(TemplateModule::assigned_sequencer(), Some(sequencer(0xa1)));
+	});
+}
+
+#[test]
+fn anyone_submits_after_fallback_timeout() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let (image_id, receipt) = fixture();
+		let call = Call::submit_proof_unsigned { image_id, receipt: receipt.clone() };
+		assert_ok!(TemplateModule::add_sequencer(RuntimeOrigin::root(), sequencer(0xa1)));
+
+		assert_noop!(
+			TemplateModule::submit_proof(signer(), image_id, receipt.clone()),
+			Error::<Test>::NotAssignedSequencer
+		);
+		assert_noop!(
+			TemplateModule::submit_proof_unsigned(RuntimeOrigin::none(), image_id, receipt.clone()),
+			Error::<Test>::NotAssignedSequencer
+		);
+		assert_eq!(
+			TemplateModule::validate_unsigned(TransactionSource::External, &call),
+			Err(InvalidTransaction::Call.into())
+		);
+
+		// No batch since genesis, so the fallback opens `SequencerFallbackTimeout` blocks in
+		System::set_block_number(50);
+		assert_ok!(TemplateModule::validate_unsigned(TransactionSource::External, &call));
+		assert_ok!(TemplateModule::submit_proof(signer(), image_id, receipt));
+		assert_eq!(TemplateModule::last_batch_at(), 50);
+	});
+}
+
+#[test]
+fn admin_manages_sequencers() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_noop!(
+			TemplateModule::add_sequencer(signer(), sequencer(1)),
+			DispatchError::BadOrigin
+		);
+
+		for id in 1..=4 {
+			assert_ok!(TemplateModule::add_sequencer(RuntimeOrigin::root(), sequencer(id)));
+		}
+		System::assert_last_event(Event::SequencerAdded { sequencer: sequencer(4) }.into());
+		assert_noop!(
+			TemplateModule::add_sequencer(RuntimeOrigin::root(), sequencer(1)),
+			Error::<Test>::AlreadySequencer
+		);
+		assert_noop!(
+			TemplateModule::add_sequencer(RuntimeOrigin::root(), sequencer(5)),
+			Error::<Test>::TooManySequencers
+		);
+
+		assert_ok!(TemplateModule::remove_sequencer(RuntimeOrigin::root(), sequencer(2)));
+		System::assert_last_event(Event::SequencerRemoved { sequencer: sequencer(2) }.into());
+		assert_eq!(
+			TemplateModule::sequencers().into_inner(),
+			vec![1, 3, 4].into_iter().map(sequencer).collect::<Vec<_>>()
+		);
+		assert_noop!(
+			TemplateModule::remove_sequencer(RuntimeOrigin::root(), sequencer(2)),
+			Error::<Test>::NotSequencer
+		);
+	});
+}
+
+#[test]
+fn rejects_account_count_mismatch() {
+	new_test_ext().execute_with(|| {
+		let journal = encode_journal(&(
+			vec![[1; 32], [2; 32]],
+			vec![90u128.to_be_bytes()],
+			vec![1, 0],
+			StateRoot::<Test>::get(),
+			[1; 32],
+			0,
+			[0xaa; 32],
+			FIXTURE_FEE.to_be_bytes(),
+			0u128.to_be_bytes(),
+			0,
+			[0; 32],
+			vec![],
+			vec![TRANSFER_APPLIED],
+			System::block_hash(0).0,
+			RollupPalletId::get().0,
+		));
+
+		assert_noop!(
+			TransferHandler::<Test>::handle(&journal),
+			Error::<Test>::AccountCountMismatch
+		);
+	});
+}
+
+#[test]
+fn rejects_empty_batch() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			TransferHandler::<Test>::handle(&batch_journal(vec![], 0, 0, [0; 32], vec![], vec![])),
+			Error::<Test>::EmptyBatch
+		);
+	});
+}
+
+#[test]
+fn rejects_journal_of_partial_words() {
+	new_test_ext().execute_with(|| {
+		let mut journal = batch_journal(vec![([1; 32], 10, 0)], 0, 0, [0; 32], vec![], vec![]);
+		journal.pop();
+		assert_noop!(TransferHandler::<Test>::handle(&journal), Error::<Test>::JournalDecodeFailed);
+	});
+}
+
+#[test]
+fn distributes_fees() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let locked = Balances::free_balance(bridge_account());
+		// [1; 32] sends 10 to [2; 32] and pays the fee of a single transfer
+		let journal = batch_journal(
+			vec![([1; 32], 8_990, 1), ([2; 32], 10_010, 0)],
+			FIXTURE_FEE,
+			0,
+			[0; 32],
+			vec![],
+			vec![TRANSFER_APPLIED],
+		);
+
+		assert_ok!(TransferHandler::<Test>::handle(&journal));
+
+		assert_eq!(Balances::free_balance(AccountId32::new([0xaa; 32])), 800);
+		// The treasury was seeded with the existential deposit at genesis
+		assert_eq!(Balances::free_balance(TreasuryAccount::get()), 1 + 200);
+		// Fees leave the ledger, so they come out of the locked funds
+		assert_eq!(Balances::free_balance(bridge_account()), locked - FIXTURE_FEE);
+		System::assert_has_event(
+			Event::FeesDistributed {
+				prover: AccountId32::new([0xaa; 32]),
+				prover_reward: 800,
+				treasury_share: 200,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn rejects_batches_for_other_chains() {
+	new_test_ext().execute_with(|| {
+		let journal = queue_journal(0, [0; 32]);
+		// Proven for the chain as it is, but the transfers would then be valid on a chain with
+		// another genesis just as well
+		frame_system::BlockHash::<Test>::insert(0, H256::repeat_byte(1));
+
+		assert_noop!(TransferHandler::<Test>::handle(&journal), Error::<Test>::DomainMismatch);
+	});
+}
+
+#[test]
+fn rejects_fee_mismatch() {
+	new_test_ext().execute_with(|| {
+		// Proven with no fee at all, so the prover gets nothing out of the senders
+		let journal = encode_journal(&(
+			vec![[1; 32], [2; 32]],
+			vec![9_990u128.to_be_bytes(), 10_010u128.to_be_bytes()],
+			vec![1, 0],
+			StateRoot::<Test>::get(),
+			[1; 32],
+			0,
+			[0xaa; 32],
+			0u128.to_be_bytes(),
+			0u128.to_be_bytes(),
+			0,
+			[0; 32],
+			vec![],
+			vec![TRANSFER_APPLIED],
+			System::block_hash(0).0,
+			RollupPalletId::get().0,
+		));
+
+		assert_noop!(TransferHandler::<Test>::handle(&journal), Error::<Test>::FeeMismatch);
+	});
+}
+
+#[test]
+fn rejects_payouts_beyond_locked_funds() {
+	new_test_ext().execute_with(|| {
+		let locked = Balances::free_balance(bridge_account());
+		let journal = batch_journal(
+			vec![([1; 32], 0, 0)],
+			0,
+			0,
+			[0; 32],
+			vec![([1; 32], locked + 1)],
+			vec![TRANSFER_APPLIED],
+		);
+
+		assert_noop!(
+			TransferHandler::<Test>::handle(&journal),
+			pallet_balances::Error::<Test>::InsufficientBalance
+		);
+	});
+}
+
+/// Journal of [1; 32] sending 10 to [2; 32], which processes the first `processed` queued
+/// transfers with the given hash of them
+fn queue_journal(processed: u64, hash: [u8; 32]) -> Vec<u8> {
+	batch_journal(
+		vec![([1; 32], 8_990, 1), ([2; 32], 10_010, 0)],
+		FIXTURE_FEE,
+		processed,
+		hash,
+		vec![],
+		vec![TRANSFER_APPLIED; processed as usize + 1],
+	)
+}
+
+#[test]
+fn emits_transaction_outcomes() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let journal = batch_journal(
+			vec![([1; 32], 8_990, 1), ([2; 32], 10_010, 0)],
+			FIXTURE_FEE,
+			0,
+			[0; 32],
+			vec![],
+			vec![
+				TRANSFER_INSUFFICIENT_BALANCE,
+				TRANSFER_APPLIED,
+				TRANSFER_OVERFLOW,
+				TRANSFER_BAD_SIGNATURE,
+				TRANSFER_BAD_NONCE,
+			],
+		);
+
+		assert_ok!(TransferHandler::<Test>::handle(&journal));
+
+		System::assert_has_event(
+			Event::TransferRejected {
+				batch_number: 0,
+				index: 0,
+				reason: TransferRejection::InsufficientBalance,
+			}
+			.into(),
+		);
+		System::assert_has_event(Event::TransferApplied { batch_number: 0, index: 1 }.into());
+		System::assert_has_event(
+			Event::TransferRejected {
+				batch_number: 0,
+				index: 2,
+				reason: TransferRejection::Overflow,
+			}
+			.into(),
+		);
+		System::assert_has_event(
+			Event::TransferRejected {
+				batch_number: 0,
+				index: 3,
+				reason: TransferRejection::BadSignature,
+			}
+			.into(),
+		);
+		System::assert_has_event(
+			Event::TransferRejected {
+				batch_number: 0,
+				index: 4,
+				reason: TransferRejection::BadNonce,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn rejects_malformed_outcomes() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(TemplateModule::queue_transfer(signer(), AccountId32::new([2; 32]), 10));
+		let queue = TemplateModule::transfer_queue();
+		let changes = vec![([1; 32], 8_990, 1), ([2; 32], 10_010, 0)];
+
+		// Unknown outcome
+		let journal = batch_journal(
+			changes.clone(),
+			FIXTURE_FEE,
+			0,
+			[0; 32],
+			vec![],
+			vec![TRANSFER_APPLIED, 9],
+		);
+		assert_noop!(TransferHandler::<Test>::handle(&journal), Error::<Test>::JournalDecodeFailed);
+
+		// Fewer outcomes than queued transfers processed
+		let journal = batch_journal(changes, FIXTURE_FEE, 1, queue_hash(&queue), vec![], vec![]);
+		assert_noop!(TransferHandler::<Test>::handle(&journal), Error::<Test>::JournalDecodeFailed);
+	});
+}
+
+#[test]
+fn queues_transfers() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		assert_ok!(TemplateModule::queue_transfer(signer(), AccountId32::new([2; 32]), 10));
+		System::assert_last_event(
+			Event::TransferQueued {
+				sender: AccountId32::new(DEPOSITOR),
+				recipient: AccountId32::new([2; 32]),
+				amount: 10,
+			}
+			.into(),
+		);
+		assert_eq!(
+			TemplateModule::transfer_queue().into_inner(),
+			vec![QueuedTransfer {
+				kind: QueuedKind::Transfer,
+				sender: DEPOSITOR,
+				recipient: [2; 32],
+				amount: 10,
+				queued_at: 1
+			}]
+		);
+
+		assert_eq!(Balances::reserved_balance(AccountId32::new(DEPOSITOR)), QUEUE_DEPOSIT);
+
+		for _ in 1..4 {
+			assert_ok!(TemplateModule::queue_transfer(signer(), AccountId32::new([2; 32]), 10));
+		}
+		assert_noop!(
+			TemplateModule::queue_transfer(signer(), AccountId32::new([2; 32]), 10),
+			Error::<Test>::QueueFull
+		);
+	});
+}
+
+#[test]
+fn queue_entries_cost_a_deposit() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			TemplateModule::queue_transfer(signer(), AccountId32::new([2; 32]), 0),
+			Error::<Test>::AmountTooSmall
+		);
+		assert_noop!(TemplateModule::deposit(signer(), 0), Error::<Test>::AmountTooSmall);
+		// Nothing to reserve the deposit from
+		assert_noop!(
+			TemplateModule::queue_transfer(
+				RuntimeOrigin::signed(AccountId32::new([1; 32])),
+				AccountId32::new([2; 32]),
+				10
+			),
+			pallet_balances::Error::<Test>::InsufficientBalance
+		);
+	});
+}
+
+#[test]
+fn processes_queue_prefix() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(TemplateModule::queue_transfer(signer(), AccountId32::new([2; 32]), 10));
+		assert_ok!(TemplateModule::queue_transfer(signer(), AccountId32::new([3; 32]), 20));
+		let queue = TemplateModule::transfer_queue();
+
+		assert_noop!(
+			TransferHandler::<Test>::handle(&queue_journal(1, queue_hash(&queue[1..2]))),
+			Error::<Test>::QueueMismatch
+		);
+		assert_noop!(
+			TransferHandler::<Test>::handle(&queue_journal(3, queue_hash(&queue))),
+			Error::<Test>::QueueMismatch
+		);
+
+		assert_ok!(TransferHandler::<Test>::handle(&queue_journal(1, queue_hash(&queue[..1]))));
+		assert_eq!(TemplateModule::transfer_queue().into_inner(), queue[1..].to_vec());
+		// Only the processed entry's deposit is returned
+		assert_eq!(Balances::reserved_balance(AccountId32::new(DEPOSITOR)), QUEUE_DEPOSIT);
+	});
+}
+
+#[test]
+fn rejects_skipped_queued_transfers() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(TemplateModule::queue_transfer(signer(), AccountId32::new([2; 32]), 10));
+
+		// The transfer has to be processed by any batch from `ForcedInclusionDelay` blocks on
+		System::set_block_number(21);
+		assert_noop!(
+			TransferHandler::<Test>::handle(&queue_journal(0, [0; 32])),
+			Error::<Test>::QueuedTransferSkipped
+		);
+
+		System::set_block_number(20);
+		assert_ok!(TransferHandler::<Test>::handle(&queue_journal(0, [0; 32])));
+		assert_eq!(TemplateModule::transfer_queue().len(), 1);
+	});
+}
+
+#[test]
+fn locks_deposits() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let locked = Balances::free_balance(bridge_account());
+
+		assert_ok!(TemplateModule::deposit(signer(), 1_000));
+
+		assert_eq!(
+			Balances::free_balance(AccountId32::new(DEPOSITOR)),
+			FIXTURE_BALANCE - 1_000 - QUEUE_DEPOSIT
+		);
+		assert_eq!(Balances::free_balance(bridge_account()), locked + 1_000);
+		assert_eq!(
+			TemplateModule::transfer_queue().into_inner(),
+			vec![QueuedTransfer {
+				kind: QueuedKind::Deposit,
+				sender: DEPOSITOR,
+				recipient: DEPOSITOR,
+				amount: 1_000,
+				queued_at: 1
+			}]
+		);
+		System::assert_last_event(
+			Event::Deposited { account: AccountId32::new(DEPOSITOR), amount: 1_000 }.into(),
+		);
+
+		assert_noop!(
+			TemplateModule::deposit(signer(), FIXTURE_BALANCE),
+			pallet_balances::Error::<Test>::InsufficientBalance
+		);
 	});
 }
 
 #[test]
-fn correct_error_for_none_value() {
+fn pays_out_withdrawals() {
 	new_test_ext().execute_with(|| {
-		// Ensure the expected error is thrown when no value is present.
+		System::set_block_number(1);
+		let locked = Balances::free_balance(bridge_account());
+		let withdrawer = AccountId32::new([1; 32]);
+		// Enough for the queue deposit
+		Balances::make_free_balance_be(&withdrawer, QUEUE_DEPOSIT);
+
 		assert_noop!(
-			TemplateModule::cause_error(RuntimeOrigin::signed(1)),
-			Error::<Test>::NoneValue
+			TemplateModule::withdraw(RuntimeOrigin::signed(withdrawer.clone()), 0),
+			Error::<Test>::AmountTooSmall
 		);
+		assert_ok!(TemplateModule::withdraw(RuntimeOrigin::signed(withdrawer.clone()), 500));
+		System::assert_last_event(
+			Event::WithdrawalQueued { account: withdrawer.clone(), amount: 500 }.into(),
+		);
+		// Nothing leaves the locked funds until a batch takes the withdrawal out of the ledger
+		assert_eq!(Balances::free_balance(&withdrawer), 0);
+
+		let queue = TemplateModule::transfer_queue();
+		let journal = batch_journal(
+			vec![([1; 32], 10_000 - 500 - FIXTURE_FEE, 0)],
+			FIXTURE_FEE,
+			1,
+			queue_hash(&queue),
+			vec![([1; 32], 500)],
+			vec![TRANSFER_APPLIED],
+		);
+		assert_ok!(TransferHandler::<Test>::handle(&journal));
+
+		assert_eq!(Balances::free_balance(&withdrawer), 500 + QUEUE_DEPOSIT);
+		assert_eq!(Balances::free_balance(bridge_account()), locked - 500 - FIXTURE_FEE);
+		assert!(TemplateModule::transfer_queue().is_empty());
+		System::assert_has_event(Event::WithdrawalPaid { account: withdrawer, amount: 500 }.into());
+	});
+}
+
+/// Set the state root to that of a ledger holding `accounts` with their balances and nonces,
+/// returning the proof of each account
+fn set_ledger(accounts: &[([u8; 32], u128, u64)]) -> Vec<Vec<([u8; 32], bool)>> {
+	let leaves: Vec<_> = accounts
+		.iter()
+		.map(|(account, balance, nonce)| transfer_core::leaf(account, *balance, *nonce))
+		.collect();
+	StateRoot::<Test>::put(transfer_core::merkle_root(leaves.clone()));
+	(0..leaves.len())
+		.map(|index| transfer_core::merkle_proof(leaves.clone(), index))
+		.collect()
+}
+
+#[test]
+fn escapes_with_ledger_balance() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let proofs = set_ledger(&[([1; 32], 300, 2), ([2; 32], 700, 0), ([3; 32], 0, 5)]);
+		let escaper = AccountId32::new([1; 32]);
+		let origin = RuntimeOrigin::signed(escaper.clone());
+
+		assert_noop!(
+			TemplateModule::escape(origin.clone(), 300, 2, proofs[0].clone()),
+			Error::<Test>::EscapeNotOpen
+		);
+
+		// No batch since genesis, so escapes open `EscapeTimeout` blocks in
+		System::set_block_number(100);
+		assert_noop!(
+			TemplateModule::escape(origin.clone(), 301, 2, proofs[0].clone()),
+			Error::<Test>::InvalidInclusionProof
+		);
+		assert_noop!(
+			TemplateModule::escape(origin.clone(), 300, 1, proofs[0].clone()),
+			Error::<Test>::InvalidInclusionProof
+		);
+		assert_noop!(
+			TemplateModule::escape(origin.clone(), 300, 2, proofs[1].clone()),
+			Error::<Test>::InvalidInclusionProof
+		);
+		assert_ok!(TemplateModule::escape(origin.clone(), 300, 2, proofs[0].clone()));
+
+		assert_eq!(Balances::free_balance(&escaper), 300);
+		assert!(TemplateModule::frozen());
+		System::assert_last_event(Event::Escaped { account: escaper, amount: 300 }.into());
+		assert_noop!(
+			TemplateModule::escape(origin, 300, 2, proofs[0].clone()),
+			Error::<Test>::AlreadyExited
+		);
+	});
+}
+
+#[test]
+fn freezes_after_escape() {
+	new_test_ext().execute_with(|| {
+		let proofs = set_ledger(&[([1; 32], 300, 0), ([2; 32], 700, 0)]);
+		System::set_block_number(100);
+		assert_ok!(TemplateModule::escape(
+			RuntimeOrigin::signed(AccountId32::new([1; 32])),
+			300,
+			0,
+			proofs[0].clone()
+		));
+
+		// Other accounts can still escape once a batch lands in time, but batches no longer apply
+		LastBatchAt::<Test>::put(100);
+		assert_ok!(TemplateModule::escape(
+			RuntimeOrigin::signed(AccountId32::new([2; 32])),
+			700,
+			0,
+			proofs[1].clone()
+		));
+		assert_noop!(
+			TransferHandler::<Test>::handle(&queue_journal(0, [0; 32])),
+			Error::<Test>::RollupFrozen
+		);
+		assert_noop!(TemplateModule::deposit(signer(), 10), Error::<Test>::RollupFrozen);
+		assert_noop!(
+			TemplateModule::queue_transfer(signer(), AccountId32::new([2; 32]), 10),
+			Error::<Test>::RollupFrozen
+		);
+	});
+}
+
+#[test]
+fn escape_returns_queued_deposits() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(TemplateModule::deposit(signer(), 1_000));
+		let proofs = set_ledger(&[([1; 32], 300, 0), (DEPOSITOR, 50, 3)]);
+
+		System::set_block_number(100);
+		assert_ok!(TemplateModule::escape(signer(), 50, 3, proofs[1].clone()));
+
+		assert_eq!(Balances::free_balance(AccountId32::new(DEPOSITOR)), FIXTURE_BALANCE + 50);
+	});
+}
+
+#[test]
+fn escapes_deposits_of_accounts_not_in_ledger() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(TemplateModule::deposit(signer(), 1_000));
+		set_ledger(&[([1; 32], 300, 0)]);
+		let depositor = AccountId32::new(DEPOSITOR);
+
+		assert_noop!(TemplateModule::escape_deposits(signer()), Error::<Test>::EscapeNotOpen);
+
+		System::set_block_number(100);
+		// Nothing queued, so nothing to get out, and no reason to freeze the rollup
+		assert_noop!(
+			TemplateModule::escape_deposits(RuntimeOrigin::signed(AccountId32::new([1; 32]))),
+			Error::<Test>::NothingToEscape
+		);
+		assert_ok!(TemplateModule::escape_deposits(signer()));
+
+		assert_eq!(Balances::free_balance(&depositor), FIXTURE_BALANCE);
+		assert!(TemplateModule::frozen());
+		System::assert_last_event(Event::Escaped { account: depositor, amount: 1_000 }.into());
+		assert_noop!(TemplateModule::escape_deposits(signer()), Error::<Test>::AlreadyExited);
+	});
+}
+
+/// Deterministic xorshift, so any failure found by the fuzz tests reproduces
+struct Rng(u64);
+
+impl Rng {
+	fn next(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+
+	fn below(&mut self, bound: u64) -> usize {
+		(self.next() % bound) as usize
+	}
+
+	fn bytes(&mut self, len: usize) -> Vec<u8> {
+		(0..len).map(|_| self.next() as u8).collect()
+	}
+
+	fn balance(&mut self) -> [u8; 16] {
+		// Mostly small balances, so some batches get past the balance checks
+		let balance = match self.below(3) {
+			0 => u128::MAX - self.next() as u128,
+			_ => self.below(200) as u128,
+		};
+		balance.to_be_bytes()
+	}
+}
+
+#[test]
+fn arbitrary_journals_never_panic() {
+	new_test_ext().execute_with(|| {
+		let mut rng = Rng(0x5eed);
+
+		for _ in 0..2_000 {
+			let journal = if rng.below(2) == 0 {
+				let len = rng.below(2_048);
+				rng.bytes(len)
+			} else {
+				// Well-formed journals with arbitrary contents
+				let accounts = (0..rng.below(8)).map(|_| [rng.below(8) as u8; 32]).collect();
+				let new_balances = (0..rng.below(8)).map(|_| rng.balance()).collect();
+				let new_nonces = (0..rng.below(8)).map(|_| rng.next()).collect();
+				let exits = (0..rng.below(4))
+					.map(|_| ([rng.below(8) as u8; 32], rng.balance()))
+					.collect::<Vec<_>>();
+				// Mostly known outcomes
+				let outcomes = (0..rng.below(8)).map(|_| rng.below(6) as u8).collect::<Vec<_>>();
+				encode_journal(&(
+					accounts,
+					new_balances,
+					new_nonces,
+					StateRoot::<Test>::get(),
+					[rng.below(255) as u8; 32],
+					BatchNumber::<Test>::get(),
+					[rng.below(8) as u8; 32],
+					if rng.below(4) == 0 { rng.balance() } else { FIXTURE_FEE.to_be_bytes() },
+					rng.balance(),
+					0,
+					[0; 32],
+					exits,
+					outcomes,
+					if rng.below(4) == 0 {
+						[rng.below(255) as u8; 32]
+					} else {
+						System::block_hash(0).0
+					},
+					RollupPalletId::get().0,
+				))
+			};
+
+			let _ = frame_support::storage::with_storage_layer(|| {
+				TransferHandler::<Test>::handle(&journal)
+			});
+		}
+	});
+}
+
+#[test]
+fn arbitrary_seals_are_rejected() {
+	new_test_ext().execute_with(|| {
+		let mut rng = Rng(0xdead_beef);
+		let (image_id, receipt) = fixture();
+
+		for _ in 0..100 {
+			let mut tampered = receipt.clone();
+			tampered.segments = (0..rng.below(3))
+				.map(|index| {
+					let len = rng.below(receipt.segments[0].0.len() as u64 + 1);
+					((0..len).map(|_| rng.next() as u32).collect(), index as u32)
+				})
+				.collect();
+			if rng.below(2) == 0 {
+				let len = rng.below(512);
+				tampered.journal = rng.bytes(len);
+			}
+
+			assert!(TemplateModule::submit_proof(signer(), image_id, tampered).is_err());
+		}
 	});
 }
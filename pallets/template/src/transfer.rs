@@ -0,0 +1,304 @@
+use crate::{
+	weights::WeightInfo, BalanceOf, BatchNumber, Config, Error, Event, Frozen, Pallet,
+	ProgramHandler, ProgramKind, QueuedTransfer, StateRoot, TransferQueue, TransferRejection,
+	LOG_TARGET,
+};
+use codec::Decode;
+use frame_support::{
+	dispatch::DispatchError,
+	ensure,
+	sp_runtime::traits::{Saturating, Zero},
+	traits::{Currency, ExistenceRequirement, Get, ReservableCurrency},
+	weights::Weight,
+	BoundedVec,
+};
+use risc0_zkvm::serde::from_slice;
+use sp_std::{marker::PhantomData, vec::Vec};
+
+/// Journal committed by the transfer guest: the ledger accounts changed by the batch and their new
+/// balances and nonces (in the same order), then the previous state root, the new state root and
+/// the batch number, then the prover account, the fee charged per transfer and the total fees of
+/// the batch, then the number of queued transfers processed and the `queue_hash` of them, then the
+/// recipients and amounts of the withdrawals to pay out, then the outcome of every transaction in
+/// the batch, and last the genesis hash and rollup pallet id the signatures were verified for
+pub(crate) type TransferJournal = (
+	Vec<[u8; 32]>,
+	Vec<[u8; 16]>,
+	Vec<u64>,
+	[u8; 32],
+	[u8; 32],
+	u64,
+	[u8; 32],
+	[u8; 16],
+	[u8; 16],
+	u64,
+	[u8; 32],
+	Vec<([u8; 32], [u8; 16])>,
+	Vec<u8>,
+	[u8; 32],
+	[u8; 8],
+);
+
+/// Serialize a journal the same way the guest commits it
+#[cfg(any(test, feature = "runtime-benchmarks"))]
+pub(crate) fn encode_journal(journal: &TransferJournal) -> Vec<u8> {
+	risc0_zkvm::serde::to_vec(journal)
+		.expect("journal is serializable")
+		.into_iter()
+		.flat_map(|word| word.to_le_bytes())
+		.collect()
+}
+
+/// Hash of a prefix of the transfer queue, the same way the guest computes it: each transfer is
+/// hashed together with the hash of the transfers before it, starting from all zeroes
+pub(crate) fn queue_hash<BlockNumber>(transfers: &[QueuedTransfer<BlockNumber>]) -> [u8; 32] {
+	transfers.iter().fold([0u8; 32], |hash, transfer| {
+		transfer_core::queue_hash(
+			&hash,
+			transfer.kind.code(),
+			&transfer.sender,
+			&transfer.recipient,
+			transfer.amount,
+		)
+	})
+}
+
+/// Bytes each account takes up in the journal. The guest serializes every byte as a word, so an
+/// account id and its new balance take 32 + 16 words of 4 bytes, and its nonce another 2 words
+const JOURNAL_BYTES_PER_ACCOUNT: usize = (32 + 16) * 4 + 8;
+
+/// Bytes each transaction's outcome takes up in the journal
+const JOURNAL_BYTES_PER_OUTCOME: usize = 4;
+
+/// Outcome of a transaction from the `TRANSFER_*` code the guest commits for it, with `None`
+/// standing for an unknown code
+fn outcome(code: u8) -> Option<Result<(), TransferRejection>> {
+	match code {
+		transfer_core::TRANSFER_APPLIED => Some(Ok(())),
+		transfer_core::TRANSFER_INSUFFICIENT_BALANCE =>
+			Some(Err(TransferRejection::InsufficientBalance)),
+		transfer_core::TRANSFER_OVERFLOW => Some(Err(TransferRejection::Overflow)),
+		transfer_core::TRANSFER_BAD_SIGNATURE => Some(Err(TransferRejection::BadSignature)),
+		transfer_core::TRANSFER_BAD_NONCE => Some(Err(TransferRejection::BadNonce)),
+		_ => None,
+	}
+}
+
+/// Applies batches of the rollup ledger proven by the guest in `provers/transfer`
+pub struct TransferHandler<T>(PhantomData<T>);
+
+impl<T: Config> TransferHandler<T>
+where
+	BalanceOf<T>: From<u128>,
+{
+	/// Decode the journal, and check it is well-formed and is the next batch on top of the
+	/// current state root
+	fn decode(journal: &[u8]) -> Result<TransferJournal, DispatchError> {
+		// The journal is only as well-formed as the program that committed it, so decoding can
+		// still fail after verification. risc0 serde reads whole words, and panics on a journal
+		// that is not made of them
+		if journal.len() % 4 != 0 {
+			return Err(Error::<T>::JournalDecodeFailed.into())
+		}
+		let journal: TransferJournal =
+			from_slice(journal).map_err(|_| Error::<T>::JournalDecodeFailed)?;
+		let (
+			accounts,
+			new_balances,
+			new_nonces,
+			prev_root,
+			_,
+			batch_number,
+			_,
+			fee,
+			_,
+			queue_processed,
+			queue_prefix_hash,
+			_,
+			outcomes,
+			genesis_hash,
+			rollup_id,
+		) = &journal;
+
+		ensure!(!Frozen::<T>::get(), Error::<T>::RollupFrozen);
+		ensure!(!outcomes.is_empty(), Error::<T>::EmptyBatch);
+		ensure!(
+			accounts.len() == new_balances.len() && accounts.len() == new_nonces.len(),
+			Error::<T>::AccountCountMismatch
+		);
+		// Every queued transfer processed has an outcome, ahead of the other transactions
+		ensure!(
+			outcomes.len() as u64 >= *queue_processed &&
+				outcomes.iter().all(|code| outcome(*code).is_some()),
+			Error::<T>::JournalDecodeFailed
+		);
+
+		// Transfers signed for any other deployment of the rollup are not valid here
+		let genesis = frame_system::Pallet::<T>::block_hash(T::BlockNumber::zero());
+		ensure!(
+			genesis.as_ref() == &genesis_hash[..] && T::PalletId::get().0 == *rollup_id,
+			Error::<T>::DomainMismatch
+		);
+
+		// Only the next batch on top of the current root may apply, which also rules out replays
+		ensure!(*prev_root == StateRoot::<T>::get(), Error::<T>::StateRootMismatch);
+		ensure!(*batch_number == BatchNumber::<T>::get(), Error::<T>::BatchNumberMismatch);
+		ensure!(
+			BalanceOf::<T>::from(u128::from_be_bytes(*fee)) == T::TransferFee::get(),
+			Error::<T>::FeeMismatch
+		);
+
+		// The batch has to process the queue in order, and can only leave out transfers which
+		// have not been waiting for `ForcedInclusionDelay` yet
+		let queue = TransferQueue::<T>::get();
+		let processed = usize::try_from(*queue_processed)
+			.ok()
+			.filter(|processed| *processed <= queue.len())
+			.ok_or(Error::<T>::QueueMismatch)?;
+		ensure!(queue_hash(&queue[..processed]) == *queue_prefix_hash, Error::<T>::QueueMismatch);
+		if let Some(oldest) = queue.get(processed) {
+			let now = frame_system::Pallet::<T>::block_number();
+			ensure!(
+				now < oldest.queued_at.saturating_add(T::ForcedInclusionDelay::get()),
+				Error::<T>::QueuedTransferSkipped
+			);
+		}
+
+		Ok(journal)
+	}
+
+	/// Pay `amount` out of the locked funds
+	fn pay_out(account: &T::AccountId, amount: u128) -> Result<(), DispatchError> {
+		if amount == 0 {
+			return Ok(())
+		}
+		T::Currency::transfer(
+			&Pallet::<T>::bridge_account(),
+			account,
+			amount.into(),
+			ExistenceRequirement::KeepAlive,
+		)
+	}
+}
+
+impl<T: Config> ProgramHandler<T> for TransferHandler<T>
+where
+	BalanceOf<T>: From<u128>,
+{
+	const KIND: ProgramKind = ProgramKind::Transfer;
+
+	fn weight(journal: &[u8]) -> Weight {
+		let max_accounts = journal.len() / JOURNAL_BYTES_PER_ACCOUNT;
+		let max_transactions = journal.len() / JOURNAL_BYTES_PER_OUTCOME;
+		T::WeightInfo::apply_transfers(
+			u32::try_from(max_accounts).unwrap_or(u32::MAX),
+			T::MaxQueuedTransfers::get(),
+			u32::try_from(max_transactions).unwrap_or(u32::MAX),
+		)
+	}
+
+	fn validate(journal: &[u8]) -> Result<Vec<u8>, DispatchError> {
+		let (_, _, _, prev_root, ..) = Self::decode(journal)?;
+		Ok(prev_root.to_vec())
+	}
+
+	fn handle(journal: &[u8]) -> Result<Weight, DispatchError> {
+		let (
+			accounts,
+			new_balances,
+			new_nonces,
+			_,
+			new_root,
+			batch_number,
+			prover,
+			_,
+			fees,
+			queue_processed,
+			_,
+			exits,
+			outcomes,
+			..,
+		) = Self::decode(journal)?;
+		let account_count = u32::try_from(accounts.len()).unwrap_or(u32::MAX);
+		log::debug!(
+			target: LOG_TARGET,
+			"Applying batch {} changing {} ledger accounts with {} withdrawals",
+			batch_number,
+			account_count,
+			exits.len()
+		);
+		let decode_account = |account: [u8; 32]| {
+			T::AccountId::decode(&mut &account[..]).map_err(|_| Error::<T>::InvalidAccountId)
+		};
+
+		// Withdrawals and fees have left the ledger, so they are paid out of the funds locked by
+		// deposits. The guest only lets them out of balances it holds, so the locked funds cover
+		// them
+		for (recipient, amount) in exits {
+			let recipient = decode_account(recipient)?;
+			let amount = u128::from_be_bytes(amount);
+			Self::pay_out(&recipient, amount)?;
+			Pallet::<T>::deposit_event(Event::<T>::WithdrawalPaid {
+				account: recipient,
+				amount: amount.into(),
+			});
+		}
+
+		let prover = decode_account(prover)?;
+		let fees = u128::from_be_bytes(fees);
+		let prover_reward = T::ProverRewardShare::get().mul_floor(fees);
+		let treasury_share = fees - prover_reward;
+		Self::pay_out(&prover, prover_reward)?;
+		Self::pay_out(&T::TreasuryAccount::get(), treasury_share)?;
+
+		StateRoot::<T>::put(new_root);
+		BatchNumber::<T>::put(batch_number.saturating_add(1));
+		// Checked against the queue length when decoding
+		let queue_processed = queue_processed as usize;
+		TransferQueue::<T>::mutate(|queue| {
+			// Processed entries no longer take up the queue, so their deposits are returned
+			for queued in queue[..queue_processed].iter() {
+				if let Ok(sender) = T::AccountId::decode(&mut &queued.sender[..]) {
+					T::Currency::unreserve(&sender, T::QueueDeposit::get());
+				}
+			}
+			*queue = BoundedVec::truncate_from(queue[queue_processed..].to_vec())
+		});
+
+		Pallet::<T>::deposit_event(Event::<T>::FeesDistributed {
+			prover,
+			prover_reward: prover_reward.into(),
+			treasury_share: treasury_share.into(),
+		});
+		for (index, code) in outcomes.iter().enumerate() {
+			let index = index as u32;
+			// Checked to be known when decoding
+			match outcome(*code) {
+				Some(Ok(())) =>
+					Pallet::<T>::deposit_event(Event::<T>::TransferApplied { batch_number, index }),
+				Some(Err(reason)) => Pallet::<T>::deposit_event(Event::<T>::TransferRejected {
+					batch_number,
+					index,
+					reason,
+				}),
+				None => {},
+			}
+		}
+		Pallet::<T>::deposit_event(Event::<T>::LedgerUpdated {
+			batch_number,
+			state_root: new_root,
+			changes: accounts
+				.into_iter()
+				.zip(new_balances.into_iter().zip(new_nonces))
+				.map(|(account, (balance, nonce))| (account, u128::from_be_bytes(balance), nonce))
+				.collect(),
+		});
+
+		Ok(T::WeightInfo::apply_transfers(
+			account_count,
+			queue_processed as u32,
+			// The queued transfers are weighed separately
+			u32::try_from(outcomes.len() - queue_processed).unwrap_or(u32::MAX),
+		))
+	}
+}
@@ -0,0 +1,224 @@
+//! Weights for pallet_template
+//!
+//! These are not benchmark output: they are hand-written, conservative estimates, and the storage
+//! comments list the accesses each call is expected to make. Replace them with the output of the
+//! benchmarks on reference hardware before relying on them, and regenerate after changing the
+//! pallet, or after registering a guest whose receipts verify differently, with:
+//!
+//! ```shell
+//! cargo build --release --features runtime-benchmarks
+//! ./target/release/node-template benchmark pallet \
+//! 	--chain dev \
+//! 	--pallet pallet_template \
+//! 	--extrinsic '*' \
+//! 	--steps 50 \
+//! 	--repeat 20 \
+//! 	--output pallets/template/src/weights.rs
+//! ```
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for pallet_template.
+pub trait WeightInfo {
+	fn verify_receipt(s: u32, ) -> Weight;
+	fn apply_transfers(a: u32, q: u32, t: u32, ) -> Weight;
+	fn register_program() -> Weight;
+	fn deprecate_program() -> Weight;
+	fn remove_program() -> Weight;
+	fn add_sequencer() -> Weight;
+	fn remove_sequencer() -> Weight;
+	fn queue_transfer() -> Weight;
+	fn deposit() -> Weight;
+	fn withdraw() -> Weight;
+	fn escape(p: u32, ) -> Weight;
+}
+
+/// Weights for pallet_template using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	// Storage: TemplateModule ProgramRegistry (r:1 w:0)
+	/// The range of component `s` is `[1, 4]`.
+	fn verify_receipt(s: u32, ) -> Weight {
+		Weight::from_ref_time(21_000_000)
+			.saturating_add(Weight::from_ref_time(61_500_000_000).saturating_mul(s.into()))
+			.saturating_add(T::DbWeight::get().reads(1))
+	}
+	// Storage: TemplateModule StateRoot (r:1 w:1)
+	// Storage: TemplateModule BatchNumber (r:1 w:1)
+	// Storage: TemplateModule TransferQueue (r:1 w:1)
+	// Storage: System Account (r:259 w:259)
+	/// The range of component `a` is `[2, 1000]`.
+	/// The range of component `q` is `[0, 256]`.
+	/// The range of component `t` is `[1, 1000]`.
+	fn apply_transfers(a: u32, q: u32, t: u32, ) -> Weight {
+		Weight::from_ref_time(96_000_000)
+			.saturating_add(Weight::from_ref_time(2_400_000).saturating_mul(a.into()))
+			.saturating_add(Weight::from_ref_time(42_000_000).saturating_mul(q.into()))
+			.saturating_add(Weight::from_ref_time(1_300_000).saturating_mul(t.into()))
+			.saturating_add(T::DbWeight::get().reads(6))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(q.into())))
+			.saturating_add(T::DbWeight::get().writes(6))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(q.into())))
+	}
+	// Storage: TemplateModule ProgramRegistry (r:1 w:1)
+	fn register_program() -> Weight {
+		Weight::from_ref_time(14_000_000)
+			.saturating_add(T::DbWeight::get().reads(1))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+	// Storage: TemplateModule ProgramRegistry (r:1 w:1)
+	fn deprecate_program() -> Weight {
+		Weight::from_ref_time(15_000_000)
+			.saturating_add(T::DbWeight::get().reads(1))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+	// Storage: TemplateModule ProgramRegistry (r:1 w:1)
+	fn remove_program() -> Weight {
+		Weight::from_ref_time(15_000_000)
+			.saturating_add(T::DbWeight::get().reads(1))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+	// Storage: TemplateModule Sequencers (r:1 w:1)
+	fn add_sequencer() -> Weight {
+		Weight::from_ref_time(17_000_000)
+			.saturating_add(T::DbWeight::get().reads(1))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+	// Storage: TemplateModule Sequencers (r:1 w:1)
+	fn remove_sequencer() -> Weight {
+		Weight::from_ref_time(18_000_000)
+			.saturating_add(T::DbWeight::get().reads(1))
+			.saturating_add(T::DbWeight::get().writes(1))
+	}
+	// Storage: TemplateModule TransferQueue (r:1 w:1)
+	// Storage: System Account (r:1 w:1)
+	fn queue_transfer() -> Weight {
+		Weight::from_ref_time(39_000_000)
+			.saturating_add(T::DbWeight::get().reads(2))
+			.saturating_add(T::DbWeight::get().writes(2))
+	}
+	// Storage: TemplateModule TransferQueue (r:1 w:1)
+	// Storage: System Account (r:2 w:2)
+	fn deposit() -> Weight {
+		Weight::from_ref_time(58_000_000)
+			.saturating_add(T::DbWeight::get().reads(3))
+			.saturating_add(T::DbWeight::get().writes(3))
+	}
+	// Storage: TemplateModule TransferQueue (r:1 w:1)
+	// Storage: System Account (r:1 w:1)
+	fn withdraw() -> Weight {
+		Weight::from_ref_time(40_000_000)
+			.saturating_add(T::DbWeight::get().reads(2))
+			.saturating_add(T::DbWeight::get().writes(2))
+	}
+	// Storage: TemplateModule Frozen (r:1 w:1)
+	// Storage: TemplateModule LastBatchAt (r:1 w:0)
+	// Storage: TemplateModule Exited (r:1 w:1)
+	// Storage: TemplateModule StateRoot (r:1 w:0)
+	// Storage: TemplateModule TransferQueue (r:1 w:0)
+	// Storage: System Account (r:2 w:2)
+	/// The range of component `p` is `[0, 32]`.
+	fn escape(p: u32, ) -> Weight {
+		Weight::from_ref_time(71_000_000)
+			.saturating_add(Weight::from_ref_time(1_900_000).saturating_mul(p.into()))
+			.saturating_add(T::DbWeight::get().reads(7))
+			.saturating_add(T::DbWeight::get().writes(4))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	// Storage: TemplateModule ProgramRegistry (r:1 w:0)
+	/// The range of component `s` is `[1, 4]`.
+	fn verify_receipt(s: u32, ) -> Weight {
+		Weight::from_ref_time(21_000_000)
+			.saturating_add(Weight::from_ref_time(61_500_000_000).saturating_mul(s.into()))
+			.saturating_add(RocksDbWeight::get().reads(1))
+	}
+	// Storage: TemplateModule StateRoot (r:1 w:1)
+	// Storage: TemplateModule BatchNumber (r:1 w:1)
+	// Storage: TemplateModule TransferQueue (r:1 w:1)
+	// Storage: System Account (r:259 w:259)
+	/// The range of component `a` is `[2, 1000]`.
+	/// The range of component `q` is `[0, 256]`.
+	/// The range of component `t` is `[1, 1000]`.
+	fn apply_transfers(a: u32, q: u32, t: u32, ) -> Weight {
+		Weight::from_ref_time(96_000_000)
+			.saturating_add(Weight::from_ref_time(2_400_000).saturating_mul(a.into()))
+			.saturating_add(Weight::from_ref_time(42_000_000).saturating_mul(q.into()))
+			.saturating_add(Weight::from_ref_time(1_300_000).saturating_mul(t.into()))
+			.saturating_add(RocksDbWeight::get().reads(6))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(q.into())))
+			.saturating_add(RocksDbWeight::get().writes(6))
+			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(q.into())))
+	}
+	// Storage: TemplateModule ProgramRegistry (r:1 w:1)
+	fn register_program() -> Weight {
+		Weight::from_ref_time(14_000_000)
+			.saturating_add(RocksDbWeight::get().reads(1))
+			.saturating_add(RocksDbWeight::get().writes(1))
+	}
+	// Storage: TemplateModule ProgramRegistry (r:1 w:1)
+	fn deprecate_program() -> Weight {
+		Weight::from_ref_time(15_000_000)
+			.saturating_add(RocksDbWeight::get().reads(1))
+			.saturating_add(RocksDbWeight::get().writes(1))
+	}
+	// Storage: TemplateModule ProgramRegistry (r:1 w:1)
+	fn remove_program() -> Weight {
+		Weight::from_ref_time(15_000_000)
+			.saturating_add(RocksDbWeight::get().reads(1))
+			.saturating_add(RocksDbWeight::get().writes(1))
+	}
+	// Storage: TemplateModule Sequencers (r:1 w:1)
+	fn add_sequencer() -> Weight {
+		Weight::from_ref_time(17_000_000)
+			.saturating_add(RocksDbWeight::get().reads(1))
+			.saturating_add(RocksDbWeight::get().writes(1))
+	}
+	// Storage: TemplateModule Sequencers (r:1 w:1)
+	fn remove_sequencer() -> Weight {
+		Weight::from_ref_time(18_000_000)
+			.saturating_add(RocksDbWeight::get().reads(1))
+			.saturating_add(RocksDbWeight::get().writes(1))
+	}
+	// Storage: TemplateModule TransferQueue (r:1 w:1)
+	// Storage: System Account (r:1 w:1)
+	fn queue_transfer() -> Weight {
+		Weight::from_ref_time(39_000_000)
+			.saturating_add(RocksDbWeight::get().reads(2))
+			.saturating_add(RocksDbWeight::get().writes(2))
+	}
+	// Storage: TemplateModule TransferQueue (r:1 w:1)
+	// Storage: System Account (r:2 w:2)
+	fn deposit() -> Weight {
+		Weight::from_ref_time(58_000_000)
+			.saturating_add(RocksDbWeight::get().reads(3))
+			.saturating_add(RocksDbWeight::get().writes(3))
+	}
+	// Storage: TemplateModule TransferQueue (r:1 w:1)
+	// Storage: System Account (r:1 w:1)
+	fn withdraw() -> Weight {
+		Weight::from_ref_time(40_000_000)
+			.saturating_add(RocksDbWeight::get().reads(2))
+			.saturating_add(RocksDbWeight::get().writes(2))
+	}
+	// Storage: TemplateModule Frozen (r:1 w:1)
+	// Storage: TemplateModule LastBatchAt (r:1 w:0)
+	// Storage: TemplateModule Exited (r:1 w:1)
+	// Storage: TemplateModule StateRoot (r:1 w:0)
+	// Storage: TemplateModule TransferQueue (r:1 w:0)
+	// Storage: System Account (r:2 w:2)
+	/// The range of component `p` is `[0, 32]`.
+	fn escape(p: u32, ) -> Weight {
+		Weight::from_ref_time(71_000_000)
+			.saturating_add(Weight::from_ref_time(1_900_000).saturating_mul(p.into()))
+			.saturating_add(RocksDbWeight::get().reads(7))
+			.saturating_add(RocksDbWeight::get().writes(4))
+	}
+}
@@ -0,0 +1,155 @@
+//! Commitments shared by the transfer guest, the prover host and the pallet, which all have to
+//! hash the rollup ledger and the transfer queue the same way
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use risc0_zkvm::sha::{Impl, Sha256};
+
+/// Kind of a queued transfer, as committed in the queue hash
+pub const QUEUED_TRANSFER: u8 = 0;
+pub const QUEUED_DEPOSIT: u8 = 1;
+pub const QUEUED_WITHDRAWAL: u8 = 2;
+
+/// Outcome of a transaction in a batch, as committed by the guest
+pub const TRANSFER_APPLIED: u8 = 0;
+/// The sender could not pay the amount and the fee
+pub const TRANSFER_INSUFFICIENT_BALANCE: u8 = 1;
+/// The amount and the fee, or the recipient's new balance, do not fit in a balance
+pub const TRANSFER_OVERFLOW: u8 = 2;
+/// The transfer was not signed by its sender
+pub const TRANSFER_BAD_SIGNATURE: u8 = 3;
+/// The transfer was signed for another nonce than the sender's next one
+pub const TRANSFER_BAD_NONCE: u8 = 4;
+
+/// Context sr25519 signatures of transfers are made in, the same as Substrate uses
+pub const SIGNING_CONTEXT: &[u8] = b"substrate";
+
+/// Signature scheme of a signed transfer, numbered like the variants of Substrate's
+/// `MultiSignature`
+pub const SIGNATURE_ED25519: u8 = 0;
+pub const SIGNATURE_SR25519: u8 = 1;
+/// ecdsa signers are identified by the blake2 hash of their compressed public key, as in the
+/// runtime, and sign the blake2 hash of the payload
+pub const SIGNATURE_ECDSA: u8 = 2;
+
+/// Path from a leaf to the root: each sibling hash, and whether it is on the right
+pub type MerkleProof = Vec<([u8; 32], bool)>;
+
+pub fn hash(bytes: &[u8]) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    out.copy_from_slice(Impl::hash_bytes(bytes).as_bytes());
+    out
+}
+
+/// Leaf of an account in the ledger tree, hash(account ++ balance ++ nonce)
+pub fn leaf(account: &[u8; 32], balance: u128, nonce: u64) -> [u8; 32] {
+    let mut preimage = [0u8; 56];
+    preimage[..32].copy_from_slice(account);
+    preimage[32..48].copy_from_slice(&balance.to_be_bytes());
+    preimage[48..].copy_from_slice(&nonce.to_be_bytes());
+    hash(&preimage)
+}
+
+fn node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut preimage = [0u8; 64];
+    preimage[..32].copy_from_slice(left);
+    preimage[32..].copy_from_slice(right);
+    hash(&preimage)
+}
+
+fn next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level.chunks(2).map(|pair| match pair {
+        [left, right] => node(left, right),
+        [odd] => *odd,
+        _ => unreachable!(),
+    }).collect()
+}
+
+/// Root of the ledger tree over leaves in account order. Each level hashes pairs together and an
+/// odd node is carried up as-is. An empty ledger has an all-zero root
+pub fn merkle_root(leaves: Vec<[u8; 32]>) -> [u8; 32] {
+    if leaves.is_empty() {
+        return [0u8; 32];
+    }
+
+    let mut level = leaves;
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+/// Proof that the leaf at `index` is part of the tree over `leaves`
+pub fn merkle_proof(leaves: Vec<[u8; 32]>, mut index: usize) -> MerkleProof {
+    let mut proof = Vec::new();
+    let mut level = leaves;
+    while level.len() > 1 {
+        let sibling = index ^ 1;
+        // A node without a sibling is carried up without hashing
+        if sibling < level.len() {
+            proof.push((level[sibling], sibling > index));
+        }
+        level = next_level(&level);
+        index /= 2;
+    }
+    proof
+}
+
+/// Root of the tree `proof` leads to from `leaf`
+pub fn root_from_proof(leaf: [u8; 32], proof: &[([u8; 32], bool)]) -> [u8; 32] {
+    proof.iter().fold(leaf, |hash, (sibling, on_right)| {
+        if *on_right {
+            node(&hash, sibling)
+        } else {
+            node(sibling, &hash)
+        }
+    })
+}
+
+/// Whether `proof` leads from `leaf` to `root`
+pub fn verify_proof(leaf: [u8; 32], proof: &[([u8; 32], bool)], root: &[u8; 32]) -> bool {
+    root_from_proof(leaf, proof) == *root
+}
+
+/// Message a sender signs to authorize a transfer: the SCALE encoding of the host's
+/// `UnsignedTransactionInput`, i.e. the genesis hash of the chain and the id of the rollup pallet
+/// on it, then both account ids followed by the amount and the sender's nonce in little endian.
+/// The chain and rollup keep a signature from being valid on any other deployment
+pub fn transfer_payload(
+    genesis_hash: &[u8; 32],
+    rollup_id: &[u8; 8],
+    sender: &[u8; 32],
+    recipient: &[u8; 32],
+    amount: u128,
+    nonce: u64,
+) -> [u8; 128] {
+    let mut payload = [0u8; 128];
+    payload[..32].copy_from_slice(genesis_hash);
+    payload[32..40].copy_from_slice(rollup_id);
+    payload[40..72].copy_from_slice(sender);
+    payload[72..104].copy_from_slice(recipient);
+    payload[104..120].copy_from_slice(&amount.to_le_bytes());
+    payload[120..].copy_from_slice(&nonce.to_le_bytes());
+    payload
+}
+
+/// Fold a queued transfer into the hash of the queue entries before it. The hash of an empty
+/// queue prefix is all zeroes
+pub fn queue_hash(
+    prev: &[u8; 32],
+    kind: u8,
+    sender: &[u8; 32],
+    recipient: &[u8; 32],
+    amount: u128,
+) -> [u8; 32] {
+    let mut preimage = [0u8; 113];
+    preimage[..32].copy_from_slice(prev);
+    preimage[32] = kind;
+    preimage[33..65].copy_from_slice(sender);
+    preimage[65..97].copy_from_slice(recipient);
+    preimage[97..].copy_from_slice(&amount.to_be_bytes());
+    hash(&preimage)
+}
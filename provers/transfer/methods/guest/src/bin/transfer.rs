@@ -15,39 +15,262 @@
 #![no_main]
 #![no_std]
 
+extern crate alloc;
+
+use alloc::format;
 use risc0_zkvm::guest::env;
-use sp_std::vec::Vec;
+use blake2::{digest::consts::U32, Blake2b, Digest};
+use sp_std::{
+    collections::{btree_map::BTreeMap, btree_set::BTreeSet},
+    vec::Vec,
+};
+use transfer_core::{
+    leaf, merkle_root, queue_hash, transfer_payload, QUEUED_DEPOSIT, QUEUED_TRANSFER, QUEUED_WITHDRAWAL,
+    SIGNATURE_ECDSA, SIGNATURE_ED25519, SIGNATURE_SR25519, SIGNING_CONTEXT, TRANSFER_APPLIED, TRANSFER_BAD_NONCE, TRANSFER_BAD_SIGNATURE, TRANSFER_INSUFFICIENT_BALANCE,
+    TRANSFER_OVERFLOW,
+};
 
 risc0_zkvm::guest::entry!(main);
 
+// An account in the rollup ledger
+#[derive(Clone, Copy, Default)]
+struct Account {
+    balance: u128,
+    // Number of signed transfers applied from this account, which the next one has to be signed for
+    nonce: u64,
+}
+
+fn ledger_root(ledger: &BTreeMap<[u8; 32], Account>) -> [u8; 32] {
+    merkle_root(ledger.iter().map(|(account, Account { balance, nonce })| leaf(account, *balance, *nonce)).collect())
+}
+
+// Take the amount plus the fee from the sender, and credit the amount to the recipient, or out of
+// the ledger when there is none. Returns the outcome, and only changes the ledger when the
+// transfer is applied
+fn transfer(ledger: &mut BTreeMap<[u8; 32], Account>, sender: &[u8; 32], recipient: Option<&[u8; 32]>, amount: u128, fee: u128) -> u8 {
+    let Some(debit) = amount.checked_add(fee) else { return TRANSFER_OVERFLOW };
+    let Some(sender_balance) = ledger.get(sender).and_then(|account| account.balance.checked_sub(debit)) else {
+        return TRANSFER_INSUFFICIENT_BALANCE
+    };
+
+    let Some(recipient) = recipient else {
+        ledger.entry(*sender).or_default().balance = sender_balance;
+        return TRANSFER_APPLIED
+    };
+    // Sender and recipient may be the same account, in which case only the fee leaves it
+    let recipient_balance = if recipient == sender {
+        sender_balance
+    } else {
+        ledger.get(recipient).map_or(0, |account| account.balance)
+    };
+    let Some(recipient_balance) = recipient_balance.checked_add(amount) else { return TRANSFER_OVERFLOW };
+    ledger.entry(*sender).or_default().balance = sender_balance;
+    ledger.entry(*recipient).or_default().balance = recipient_balance;
+    TRANSFER_APPLIED
+}
+
+fn blake2_256(bytes: &[u8]) -> [u8; 32] {
+    Blake2b::<U32>::digest(bytes).into()
+}
+
+// Ledger account of a signer, the same as the runtime derives it from a `MultiSigner`: the public
+// key itself for sr25519 and ed25519, and the blake2 hash of the compressed public key for ecdsa
+fn signer_account(scheme: u8, signer: &[u8]) -> Option<[u8; 32]> {
+    match scheme {
+        SIGNATURE_ED25519 | SIGNATURE_SR25519 => signer.try_into().ok(),
+        SIGNATURE_ECDSA if signer.len() == 33 => Some(blake2_256(signer)),
+        _ => None,
+    }
+}
+
+// Whether `signature` is the signer's signature of `payload`, as `MultiSignature::verify` checks it
+fn verify_signature(scheme: u8, signer: &[u8], payload: &[u8], signature: &[u8]) -> bool {
+    match scheme {
+        SIGNATURE_ED25519 => {
+            let (Ok(public), Ok(signature)) = (ed25519_dalek::PublicKey::from_bytes(signer), ed25519_dalek::Signature::from_bytes(signature)) else {
+                return false
+            };
+            ed25519_dalek::Verifier::verify(&public, payload, &signature).is_ok()
+        },
+        SIGNATURE_SR25519 => {
+            let (Ok(public), Ok(signature)) = (schnorrkel::PublicKey::from_bytes(signer), schnorrkel::Signature::from_bytes(signature)) else {
+                return false
+            };
+            public.verify_simple(SIGNING_CONTEXT, payload, &signature).is_ok()
+        },
+        // Recoverable signatures of the blake2 hash of the payload, which have to recover to the
+        // signer's key
+        SIGNATURE_ECDSA => {
+            if signature.len() != 65 {
+                return false
+            }
+            let (Ok(ecdsa), Some(recovery_id)) = (k256::ecdsa::Signature::from_slice(&signature[..64]), k256::ecdsa::RecoveryId::from_byte(signature[64])) else {
+                return false
+            };
+            k256::ecdsa::VerifyingKey::recover_from_prehash(&blake2_256(payload), &ecdsa, recovery_id)
+                .map_or(false, |key| key.to_encoded_point(true).as_bytes() == signer)
+        },
+        _ => false,
+    }
+}
+
 pub fn main() {
+    // Rollup state root and batch number currently stored on-chain, which this batch builds on
+    let prev_root = env::read::<[u8; 32]>();
+    let batch_number = env::read::<u64>();
+    // Account credited with the prover's share of the fees, and the fee charged per transfer
+    let prover = env::read::<[u8; 32]>();
+    let fee_bytes = env::read::<[u8; 16]>();
+    // Genesis hash of the chain and id of the rollup pallet the batch is for. Signatures are only
+    // valid for these, and the pallet checks them against its own
+    let genesis_hash = env::read::<[u8; 32]>();
+    let rollup_id = env::read::<[u8; 8]>();
+    // The whole rollup ledger as of `prev_root`: 32-byte account ids, in the same order as the
+    // balances and nonces
+    let accounts = env::read::<Vec<[u8; 32]>>();
     let balances_bytes = env::read::<Vec<[u8; 16]>>();
-    let transfers_with_indexed_accounts_bytes = env::read::<Vec<(usize, usize, [u8; 16])>>();
+    let nonces = env::read::<Vec<u64>>();
+    // Prefix of the on-chain transfer queue: kind, sender, recipient and amount
+    let queued_transfers = env::read::<Vec<(u8, [u8; 32], [u8; 32], [u8; 16])>>();
+    // Transfers signed by their senders: the signature scheme and the sender's public key, then
+    // the recipient, amount, the sender's nonce and the signature
+    let transfers_bytes = env::read::<Vec<(u8, Vec<u8>, [u8; 32], [u8; 16], u64, Vec<u8>)>>();
+
+    assert_eq!(accounts.len(), balances_bytes.len(), "Each account must have exactly one balance");
+    assert_eq!(accounts.len(), nonces.len(), "Each account must have exactly one nonce");
+    // Accounts come in byte order, without duplicates, so the same ledger is always the same input
+    assert!(accounts.windows(2).all(|pair| pair[0] < pair[1]), "Ledger accounts must be in canonical order");
+
+    let mut ledger: BTreeMap<[u8; 32], Account> = accounts.into_iter().zip(balances_bytes.into_iter().zip(nonces).map(|(balance, nonce)| {
+        Account { balance: u128::from_be_bytes(balance), nonce }
+    })).collect();
+    // Being a map, the ledger is in account order, which the root is computed over
+    assert_eq!(ledger_root(&ledger), prev_root, "Ledger does not match the previous state root");
+
+    let fee = u128::from_be_bytes(fee_bytes);
+    let mut fees: u128 = 0;
+    let mut touched = BTreeSet::new();
+    let mut exits: Vec<([u8; 32], [u8; 16])> = Vec::new();
+    // Outcome of every transaction, queued ones first. Transactions which can't be applied are
+    // left out instead of failing the whole batch
+    let mut outcomes: Vec<u8> = Vec::with_capacity(queued_transfers.len() + transfers_bytes.len());
+
+    // Queued transfers go first, in queue order. The pallet checks this hash against its queue, so
+    // they can't be left out or changed
+    let mut queue_prefix_hash = [0u8; 32];
+    for (kind, sender, recipient, amount_bytes) in queued_transfers.iter() {
+        let amount = u128::from_be_bytes(*amount_bytes);
+        queue_prefix_hash = queue_hash(&queue_prefix_hash, *kind, sender, recipient, amount);
+
+        let outcome = match *kind {
+            // The funds are already locked on-chain, so deposits always go through. The ledger
+            // never holds more than the locked funds, so this can't overflow
+            QUEUED_DEPOSIT => {
+                let account = ledger.entry(*recipient).or_default();
+                account.balance = account.balance.checked_add(amount).unwrap();
+                touched.insert(*recipient);
+                TRANSFER_APPLIED
+            },
+            QUEUED_TRANSFER | QUEUED_WITHDRAWAL => {
+                let to = (*kind == QUEUED_TRANSFER).then_some(recipient);
+                let outcome = transfer(&mut ledger, sender, to, amount, fee);
+                if outcome == TRANSFER_APPLIED {
+                    fees = fees.checked_add(fee).unwrap();
+                    touched.insert(*sender);
+                    match to {
+                        Some(recipient) => { touched.insert(*recipient); },
+                        // Paid out on-chain from the locked funds once the batch is verified
+                        None => exits.push((*recipient, *amount_bytes)),
+                    }
+                }
+                outcome
+            },
+            _ => panic!("Unknown queued transfer kind {}", kind),
+        };
+        outcomes.push(outcome);
+    }
+
+    // Queued transfers were authorized by the extrinsic that queued them, but these have to be signed
+    let mut signature_cycles = 0;
+    for (scheme, signer, recipient, amount_bytes, nonce, signature) in transfers_bytes.iter() {
+        let amount = u128::from_be_bytes(*amount_bytes);
+        // The transfer is rejected before touching the ledger when there is no such signer
+        let Some(sender) = signer_account(*scheme, signer) else {
+            outcomes.push(TRANSFER_BAD_SIGNATURE);
+            continue
+        };
+        let sender = &sender;
+
+        let start = env::get_cycle_count();
+        let payload = transfer_payload(&genesis_hash, &rollup_id, sender, recipient, amount, *nonce);
+        let signed = verify_signature(*scheme, signer, &payload, signature);
+        signature_cycles += env::get_cycle_count() - start;
 
-    let mut balances: Vec<u128> = balances_bytes.clone().into_iter().map(|balance| {
-        u128::from_be_bytes(balance)
-    }).collect();
+        // Each signed transfer can only be applied once, as the sender's nonce moves past it. A
+        // transfer that is left out keeps the nonce, so it can still go in a later batch
+        let expected_nonce = ledger.get(sender).map_or(0, |account| account.nonce);
+        let outcome = if !signed {
+            TRANSFER_BAD_SIGNATURE
+        } else if *nonce != expected_nonce {
+            TRANSFER_BAD_NONCE
+        } else {
+            // The sender pays the fee on top of the amount, which is paid out on-chain
+            transfer(&mut ledger, sender, Some(recipient), amount, fee)
+        };
+        if outcome == TRANSFER_APPLIED {
+            ledger.entry(*sender).or_default().nonce = expected_nonce + 1;
+            fees = fees.checked_add(fee).unwrap();
+            touched.insert(*sender);
+            touched.insert(*recipient);
+        }
+        outcomes.push(outcome);
+    }
 
-    let transfers_with_indexed_accounts: Vec<(usize, usize, u128)> =
-        transfers_with_indexed_accounts_bytes.clone().into_iter().map(|(sender_index, recipient_index, balance)| {
-            (sender_index, recipient_index, u128::from_be_bytes(balance))
-        }).collect();
+    if !transfers_bytes.is_empty() {
+        env::log(&format!(
+            "Verified {} signatures in {} cycles, {} cycles per signature",
+            transfers_bytes.len(),
+            signature_cycles,
+            signature_cycles / transfers_bytes.len()
+        ));
+    }
 
-    transfers_with_indexed_accounts.into_iter().for_each(|(sender_index, recipient_index, transfer_balance)| {
-        let sender_balance = balances[sender_index];
-        let recipient_balance = balances[recipient_index];
+    let new_root = ledger_root(&ledger);
 
-        // TODO: This shouldn't fail on bad transactions, we should take the bad transactions out
-        balances[sender_index] = sender_balance.checked_sub(transfer_balance).expect("Insufficient balance for transfer");
-        balances[recipient_index] =  recipient_balance.checked_add(transfer_balance).unwrap();
-    });
+    // Anyone can rebuild the ledger from the changes of each batch
+    let mut changed_accounts: Vec<[u8; 32]> = Vec::with_capacity(touched.len());
+    let mut new_balances_bytes: Vec<[u8; 16]> = Vec::with_capacity(touched.len());
+    let mut new_nonces: Vec<u64> = Vec::with_capacity(touched.len());
+    for account in touched {
+        let Account { balance, nonce } = ledger[&account];
+        changed_accounts.push(account);
+        new_balances_bytes.push(balance.to_be_bytes());
+        new_nonces.push(nonce);
+    }
 
-    let new_balances_bytes: Vec<[u8; 16]> = balances.into_iter().map(|b| b.to_be_bytes()).collect();
-    
     env::commit(&(
-        // Old balances
-        balances_bytes,
+        // Accounts changed by this batch, which the balances and nonces below belong to
+        changed_accounts,
         // New balances
-        new_balances_bytes
+        new_balances_bytes,
+        // New nonces
+        new_nonces,
+        prev_root,
+        new_root,
+        batch_number,
+        prover,
+        fee_bytes,
+        // Total fees paid by the senders in this batch
+        fees.to_be_bytes(),
+        // Number of queued transfers processed, and the hash of them
+        queued_transfers.len() as u64,
+        queue_prefix_hash,
+        // Recipients and amounts of the withdrawals to pay out on-chain
+        exits,
+        // One of the `TRANSFER_*` outcomes per transaction, in the order they were processed
+        outcomes,
+        // Chain and rollup the signatures were verified for
+        genesis_hash,
+        rollup_id,
     ))
 }
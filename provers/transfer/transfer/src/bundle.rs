@@ -0,0 +1,121 @@
+use risc0_zkvm::{serde::from_slice, SegmentReceipt, SessionReceipt};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use subxt::ext::sp_core::crypto::AccountId32;
+
+use crate::{
+    error::ProverError,
+    prover::{Journal, ProvenBatch, TRANSFER_ID},
+};
+
+// Version of the bundle format written by this host. Bundles of any other version are rejected
+pub const BUNDLE_VERSION: u32 = 1;
+pub const DEFAULT_BUNDLE_PATH: &str = "./bundle.json";
+
+// A proven batch saved to a file, so it can be submitted later, or by another host, when the node
+// is not reachable right after proving
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReceiptBundle {
+    pub version: u32,
+    pub image_id: [u32; 8],
+    // Hex of the journal bytes
+    pub journal: String,
+    pub segments: Vec<BundleSegment>,
+    // Ledger accounts the batch changes
+    pub accounts: Vec<AccountId32>,
+    pub metadata: BundleMetadata,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BundleSegment {
+    pub index: u32,
+    // Hex of the little endian seal words
+    pub seal: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BundleMetadata {
+    pub batch_number: u64,
+    // Hash of the journal, which identifies the batch
+    pub batch_hash: String,
+    pub prev_root: String,
+    pub new_root: String,
+    pub transactions: usize,
+    pub proving_time_ms: u128,
+    // Seconds since the unix epoch
+    pub created_at: u64,
+}
+
+impl ReceiptBundle {
+    pub fn new(batch: &ProvenBatch, proving_time: Duration) -> ReceiptBundle {
+        let journal = &batch.journal;
+        ReceiptBundle {
+            version: BUNDLE_VERSION,
+            image_id: TRANSFER_ID,
+            journal: array_bytes::bytes2hex("0x", &batch.receipt.journal),
+            segments: batch.receipt.segments.iter().map(|segment| BundleSegment {
+                index: segment.index,
+                seal: array_bytes::bytes2hex("0x", segment.seal.iter().flat_map(|word| word.to_le_bytes()).collect::<Vec<u8>>()),
+            }).collect(),
+            accounts: journal.0.iter().map(|account| AccountId32::from(*account)).collect(),
+            metadata: BundleMetadata {
+                batch_number: journal.5,
+                batch_hash: array_bytes::bytes2hex("0x", transfer_core::hash(&batch.receipt.journal)),
+                prev_root: array_bytes::bytes2hex("0x", journal.3),
+                new_root: array_bytes::bytes2hex("0x", journal.4),
+                transactions: journal.12.len(),
+                proving_time_ms: proving_time.as_millis(),
+                created_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs()),
+            },
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), ProverError> {
+        Ok(std::fs::write(path, serde_json::to_string_pretty(self)?)?)
+    }
+
+    pub fn load(path: &str) -> Result<ReceiptBundle, ProverError> {
+        let bundle: ReceiptBundle = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        if bundle.version != BUNDLE_VERSION {
+            return Err(ProverError::InvalidBundle("unsupported version"));
+        }
+        Ok(bundle)
+    }
+
+    // The journal, decoded without verifying the receipt
+    pub fn journal(&self) -> Result<Journal, ProverError> {
+        Ok(from_slice(&self.journal_bytes()?)?)
+    }
+
+    // risc0 serde reads whole words, and panics on a journal that is not made of them
+    fn journal_bytes(&self) -> Result<Vec<u8>, ProverError> {
+        let journal = array_bytes::hex2bytes(&self.journal).map_err(|_| ProverError::InvalidBundle("invalid hex"))?;
+        if journal.len() % 4 != 0 {
+            return Err(ProverError::InvalidBundle("journal is not made of words"));
+        }
+        Ok(journal)
+    }
+
+    // The receipt, verified against the image id of this host's guest, as proving left it
+    pub fn into_proven(self) -> Result<ProvenBatch, ProverError> {
+        if self.image_id != TRANSFER_ID {
+            return Err(ProverError::InvalidBundle("proven by another guest than this host's"));
+        }
+        let journal = self.journal_bytes()?;
+        let hex = |value: &str| array_bytes::hex2bytes(value).map_err(|_| ProverError::InvalidBundle("invalid hex"));
+        let mut segments = Vec::with_capacity(self.segments.len());
+        for BundleSegment { index, seal } in self.segments {
+            let seal = hex(&seal)?;
+            if seal.len() % 4 != 0 {
+                return Err(ProverError::InvalidBundle("seal is not made of words"));
+            }
+            let seal = seal.chunks_exact(4).map(|word| u32::from_le_bytes(word.try_into().unwrap())).collect();
+            segments.push(SegmentReceipt { seal, index });
+        }
+        let receipt = SessionReceipt { segments, journal };
+
+        receipt.verify(TRANSFER_ID).map_err(|err| ProverError::Verification(format!("{:?}", err)))?;
+        let journal = from_slice(&receipt.journal)?;
+        Ok(ProvenBatch { receipt, journal })
+    }
+}
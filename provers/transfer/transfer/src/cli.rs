@@ -1,13 +1,19 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use subxt::ext::{
-    sp_core::sr25519::Public,
+    sp_core::{crypto::AccountId32, H256},
 };
 
+use risc_roll_prover::input::Scheme;
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Option<SubCommand>,
+    #[clap(long, global = true, env = "RISC_ROLL_CONFIG", help = "TOML config file with node_url, signer_suri and signer_keyfile. Defaults to ./prover.toml if it exists")]
+    pub config: Option<String>,
+    #[clap(long, global = true, env = "RISC_ROLL_NODE_URL", help = "Websocket URL of the node. Defaults to ws://127.0.0.1:9944")]
+    pub node_url: Option<String>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -16,14 +22,100 @@ pub enum SubCommand {
     Sign {
         #[clap(short, help = "Secret key of sender, which wil also be used to generate account id for \"from\" field", required = true)]
         suri: String,
-        #[clap(short, help = "Hex string of public key of receiver", required = true)]
-        to: Public,
+        #[clap(short = 'k', long, value_enum, help = "Type of the sender's key", default_value = "sr25519")]
+        scheme: Scheme,
+        #[clap(short, help = "Account id of receiver, as SS58 or hex", required = true)]
+        to: AccountId32,
         #[clap(short, help = "Amount to transfer from sender to receiver", required = true)]
         amount: u128,
+        #[clap(short, help = "Nonce of the sender. Defaults to the sender's next nonce in the local ledger, synced first, or zero with -f")]
+        nonce: Option<u64>,
+        #[clap(short, long, help = "Local copy of the rollup ledger, synced before reading the nonce", default_value = "./ledger.json")]
+        ledger: String,
+        #[clap(short, long, help = "Sign for the pallet's mock runtime, which fixtures are proven for, instead of the node's chain")]
+        fixture: bool,
     },
-    /// Run the prover
+    /// Run the prover, proving the transactions and submitting the batch
     Run {
         #[clap(short, help = "Override local file path for file containin transactions", default_value = "./transactions.json")]
         transactions_file_path: String,
+        #[clap(short, long, help = "Local copy of the rollup ledger, kept up to date with each submitted batch", default_value = "./ledger.json")]
+        ledger: String,
+        #[clap(short, long, help = "Submit the proof as an unsigned transaction, so no funded account is needed")]
+        unsigned: bool,
+        #[clap(short, long, help = "File to save the receipt bundle to before submitting, so it can be submitted again if that fails", default_value = "./bundle.json")]
+        output: String,
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+    /// Prove the transactions and save the receipt bundle, without submitting it
+    Prove {
+        #[clap(short, help = "Override local file path for file containin transactions", default_value = "./transactions.json")]
+        transactions_file_path: String,
+        #[clap(short, long, help = "Local copy of the rollup ledger, which has to match the chain's state root", default_value = "./ledger.json")]
+        ledger: String,
+        #[clap(short, long, help = "File to save the receipt bundle to", default_value = "./bundle.json")]
+        output: String,
+        #[clap(long, help = "Prove against the state in a snapshot file, JSON or SCALE if it ends in .scale, instead of the node's and the ledger's")]
+        state_file: Option<String>,
+        #[clap(long, requires = "state_file", conflicts_with_all = ["signer_suri", "signer_keyfile"], help = "Account the prover's share of the fees is paid to, as SS58 or hex, instead of the signer's. Only with --state-file, as nothing is signed")]
+        prover: Option<AccountId32>,
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+    /// Submit a receipt bundle saved by prove or run
+    Submit {
+        #[clap(help = "Receipt bundle file", default_value = "./bundle.json")]
+        bundle: String,
+        #[clap(short, long, help = "Local copy of the rollup ledger, kept up to date with each submitted batch", default_value = "./ledger.json")]
+        ledger: String,
+        #[clap(short, long, help = "Submit the proof as an unsigned transaction, so no funded account is needed")]
+        unsigned: bool,
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+    /// Print the metadata and journal of a receipt bundle
+    Inspect {
+        #[clap(help = "Receipt bundle file", default_value = "./bundle.json")]
+        bundle: String,
+    },
+    /// Replay the batches applied on-chain into the local ledger, which has to match the chain's state root before proving
+    Sync {
+        #[clap(short, long, help = "Local copy of the rollup ledger", default_value = "./ledger.json")]
+        ledger: String,
+    },
+    /// Save the rollup state at a block, with the ledger matching it, for proving without a node
+    Snapshot {
+        #[clap(short, long, help = "Hash of the block to take the state at. Defaults to the last finalized block")]
+        block_hash: Option<H256>,
+        #[clap(short, long, help = "Local copy of the rollup ledger, used if it is synced to the block", default_value = "./ledger.json")]
+        ledger: String,
+        #[clap(short, long, help = "File to save the snapshot to, JSON or SCALE if it ends in .scale", default_value = "./state.json")]
+        output: String,
+    },
+    /// Withdraw the whole balance of an account from the last state root, for when no batches have landed for a while
+    Escape {
+        #[clap(short, help = "Secret key of the account to withdraw to", required = true)]
+        suri: String,
+        #[clap(short, long, help = "Local copy of the rollup ledger", default_value = "./ledger.json")]
+        ledger: String,
+    },
+    /// Prove transactions against a fixed starting state, without a node, and write the receipt as a fixture for the pallet's benchmarks and tests
+    Fixture {
+        #[clap(short, help = "Override local file path for file containin transactions, signed with sign -f", default_value = "../../pallets/template/fixtures/transactions.json")]
+        transactions_file_path: String,
+        #[clap(short, help = "Segment size limit as a power of two. Lower values split the proof into more segments")]
+        segment_limit_po2: Option<u32>,
+        #[clap(short, help = "File path to write the fixture to", required = true)]
+        output: String,
     },
 }
+
+// Key of the prover, which proves batches for its own account and signs their submissions
+#[derive(Args, Debug)]
+pub struct SignerArgs {
+    #[clap(long, env = "RISC_ROLL_SIGNER_SURI", hide_env_values = true, conflicts_with = "signer_keyfile", help = "Secret URI of the key submitting batches, which is paid the prover's share of the fees")]
+    pub signer_suri: Option<String>,
+    #[clap(long, env = "RISC_ROLL_SIGNER_KEYFILE", help = "File holding the secret URI of the key submitting batches")]
+    pub signer_keyfile: Option<String>,
+}
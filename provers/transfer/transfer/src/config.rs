@@ -0,0 +1,60 @@
+use serde::Deserialize;
+use subxt::ext::sp_core::{sr25519::Pair as SubxtPair, Pair as SubxtPairT};
+
+use crate::error::ProverError;
+
+pub const DEFAULT_NODE_URL: &str = "ws://127.0.0.1:9944";
+// Read when it exists, so a deployment can keep its settings next to the ledger
+pub const DEFAULT_CONFIG_PATH: &str = "./prover.toml";
+
+// Where the prover connects to and which key it signs with. Command line options and environment
+// variables take precedence over the config file
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProverConfig {
+    pub node_url: Option<String>,
+    // Secret URI of the key, like `//Alice` or a hex seed
+    pub signer_suri: Option<String>,
+    // File holding the secret URI, which keeps it out of the command line and the config
+    pub signer_keyfile: Option<String>,
+}
+
+impl ProverConfig {
+    // Load the config file at `path`, or the default one if there is one
+    pub fn load(path: Option<&str>) -> Result<ProverConfig, ProverError> {
+        let contents = match path {
+            Some(path) => std::fs::read_to_string(path)?,
+            None => match std::fs::read_to_string(DEFAULT_CONFIG_PATH) {
+                Ok(contents) => contents,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(ProverConfig::default()),
+                Err(err) => return Err(err.into()),
+            },
+        };
+        toml::from_str(&contents).map_err(ProverError::Config)
+    }
+
+    // Settings of `overrides` win over these. A signer given either way replaces the other kind, so
+    // a suri on the command line is not shadowed by a keyfile in the config
+    pub fn merge(self, overrides: ProverConfig) -> ProverConfig {
+        let signer_overridden = overrides.signer_suri.is_some() || overrides.signer_keyfile.is_some();
+        ProverConfig {
+            node_url: overrides.node_url.or(self.node_url),
+            signer_suri: if signer_overridden { overrides.signer_suri } else { self.signer_suri },
+            signer_keyfile: if signer_overridden { overrides.signer_keyfile } else { self.signer_keyfile },
+        }
+    }
+
+    pub fn node_url(&self) -> &str {
+        self.node_url.as_deref().unwrap_or(DEFAULT_NODE_URL)
+    }
+
+    // The configured key, if there is one
+    pub fn signer(&self) -> Result<Option<SubxtPair>, ProverError> {
+        let suri = match (&self.signer_suri, &self.signer_keyfile) {
+            (Some(suri), _) => suri.clone(),
+            (None, Some(keyfile)) => std::fs::read_to_string(keyfile)?.trim().to_string(),
+            (None, None) => return Ok(None),
+        };
+        SubxtPair::from_string(&suri, None).map(Some).map_err(|_| ProverError::InvalidSecret)
+    }
+}
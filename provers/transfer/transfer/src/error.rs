@@ -0,0 +1,93 @@
+use std::fmt;
+
+// Everything that can go wrong while proving and submitting a batch
+#[derive(Debug)]
+pub enum ProverError {
+    // Talking to the node failed
+    Rpc(subxt::Error),
+    // Reading or writing a local file failed
+    Io(std::io::Error),
+    // A local file is not valid JSON of the expected shape
+    Json(serde_json::Error),
+    // Executing or proving the guest failed
+    Zkvm(anyhow::Error),
+    // The receipt does not verify against the transfer image id
+    Verification(String),
+    // The journal is not the one the transfer guest commits
+    Journal(risc0_zkvm::serde::Error),
+    // The secret URI is not a valid key. The secret is left out, so it doesn't end up in logs
+    InvalidSecret,
+    // The config file is not valid TOML of the expected shape
+    Config(toml::de::Error),
+    // The receipt bundle file is not one this host can submit
+    InvalidBundle(&'static str),
+    // The state snapshot file is not one batches can be proven against
+    InvalidSnapshot(&'static str),
+    // There is nothing to prove
+    EmptyBatch,
+    // The local ledger does not match the rollup state on-chain, so it has to be synced
+    LedgerBehind { path: String },
+    // The ledger misses batches, so it has to be synced from genesis
+    MissingBatches { batch_number: u64 },
+    // The chain did not report something it always should, like the event of an applied batch
+    UnexpectedChainState(&'static str),
+    // The prover was created without a node, but the operation needs one
+    Offline,
+    // The prover was created without a key, but the operation needs one
+    NoSigner,
+}
+
+impl fmt::Display for ProverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProverError::Rpc(err) => write!(f, "Node request failed: {}", err),
+            ProverError::Io(err) => write!(f, "File access failed: {}", err),
+            ProverError::Json(err) => write!(f, "Invalid JSON: {}", err),
+            ProverError::Zkvm(err) => write!(f, "Guest failed: {}", err),
+            ProverError::Verification(err) => write!(f, "Receipt does not verify, did you specify the correct image ID? {}", err),
+            ProverError::Journal(err) => write!(f, "Journal is not a transfer journal: {}", err),
+            ProverError::InvalidSecret => write!(f, "Invalid secret key"),
+            ProverError::Config(err) => write!(f, "Invalid config file: {}", err),
+            ProverError::InvalidBundle(why) => write!(f, "Invalid receipt bundle: {}", why),
+            ProverError::InvalidSnapshot(why) => write!(f, "Invalid state snapshot: {}", why),
+            ProverError::EmptyBatch => write!(f, "Transactions must not be empty!"),
+            ProverError::LedgerBehind { path } => write!(f, "Ledger at {} is behind the chain, run the sync subcommand first", path),
+            ProverError::MissingBatches { batch_number } => write!(f, "Missing batches before batch {}, the ledger has to be synced from genesis", batch_number),
+            ProverError::UnexpectedChainState(what) => write!(f, "Unexpected chain state: {}", what),
+            ProverError::Offline => write!(f, "No node to talk to"),
+            ProverError::NoSigner => write!(f, "No key to sign with, pass --signer-suri or --signer-keyfile"),
+        }
+    }
+}
+
+impl std::error::Error for ProverError {}
+
+impl From<subxt::Error> for ProverError {
+    fn from(err: subxt::Error) -> Self {
+        ProverError::Rpc(err)
+    }
+}
+
+impl From<std::io::Error> for ProverError {
+    fn from(err: std::io::Error) -> Self {
+        ProverError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ProverError {
+    fn from(err: serde_json::Error) -> Self {
+        ProverError::Json(err)
+    }
+}
+
+impl From<anyhow::Error> for ProverError {
+    fn from(err: anyhow::Error) -> Self {
+        ProverError::Zkvm(err)
+    }
+}
+
+impl From<risc0_zkvm::serde::Error> for ProverError {
+    fn from(err: risc0_zkvm::serde::Error) -> Self {
+        ProverError::Journal(err)
+    }
+}
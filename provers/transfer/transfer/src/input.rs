@@ -2,49 +2,141 @@ use serde::{Deserialize, Serialize};
 // use serde::json;
 use subxt::ext::{
     sp_core::{
-        Pair as PairT,
-        sr25519::{Pair, Public, Signature}
+        crypto::{AccountId32, Pair as PairT},
+        ecdsa, ed25519, sr25519,
     },
+    sp_runtime::{traits::{IdentifyAccount, Verify}, MultiSignature, MultiSigner},
 };
 use codec::{Decode, Encode};
+use transfer_core::{SIGNATURE_ECDSA, SIGNATURE_ED25519, SIGNATURE_SR25519};
 
-const EXPECTED_FILE_PATH: &str = "./transactions.json";
+use crate::{error::ProverError, ledger::Ledger};
+
+pub const EXPECTED_FILE_PATH: &str = "./transactions.json";
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TransactionInput {
-    pub sender: Public,
-    pub recipient: Public,
+    // Public key of the sender, whose ledger account is derived from it the same way the runtime
+    // derives accounts
+    pub sender: MultiSigner,
+    pub recipient: AccountId32,
     pub amount: u128,
-    pub signature: Signature,
+    // Number of transfers from the sender applied before this one
+    pub nonce: u64,
+    pub signature: MultiSignature,
 }
 
+impl TransactionInput {
+    // Ledger account of the sender
+    pub fn sender_account(&self) -> AccountId32 {
+        self.sender.clone().into_account()
+    }
+
+    // What the sender signed, for the given chain and rollup
+    pub fn payload(&self, domain: ([u8; 32], [u8; 8])) -> Vec<u8> {
+        UnsignedTransactionInput {
+            genesis_hash: domain.0,
+            rollup_id: domain.1,
+            sender: self.sender_account(),
+            recipient: self.recipient.clone(),
+            amount: self.amount,
+            nonce: self.nonce,
+        }.encode()
+    }
+
+    // Whether the transfer is signed by its sender. The guest checks the same, so transfers which
+    // fail this are only left out of the batch
+    pub fn verify(&self, domain: ([u8; 32], [u8; 8])) -> bool {
+        self.signature.verify(self.payload(domain).as_slice(), &self.sender_account())
+    }
+
+    // Signature scheme, public key and signature bytes, as the guest takes them
+    pub fn signature_parts(&self) -> (u8, Vec<u8>, Vec<u8>) {
+        let signer = match &self.sender {
+            MultiSigner::Ed25519(public) => (SIGNATURE_ED25519, public.0.to_vec()),
+            MultiSigner::Sr25519(public) => (SIGNATURE_SR25519, public.0.to_vec()),
+            MultiSigner::Ecdsa(public) => (SIGNATURE_ECDSA, public.0.to_vec()),
+        };
+        let signature = match &self.signature {
+            MultiSignature::Ed25519(signature) => signature.0.to_vec(),
+            MultiSignature::Sr25519(signature) => signature.0.to_vec(),
+            MultiSignature::Ecdsa(signature) => signature.0.to_vec(),
+        };
+        // A signature of another scheme than the key never verifies in the guest
+        (signer.0, signer.1, signature)
+    }
+}
+
+// Key types `sign` can sign with
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum Scheme {
+    Sr25519,
+    Ed25519,
+    Ecdsa,
+}
+
+// Key pair of the given scheme from a secret URI, as a `MultiSigner` and a way to sign with it
+fn signer_pair(scheme: Scheme, suri: &str) -> Result<(MultiSigner, Box<dyn Fn(&[u8]) -> MultiSignature>), ProverError> {
+    let invalid = |_| ProverError::InvalidSecret;
+    Ok(match scheme {
+        Scheme::Sr25519 => {
+            let pair = sr25519::Pair::from_string(suri, None).map_err(invalid)?;
+            (pair.public().into(), Box::new(move |payload| pair.sign(payload).into()))
+        },
+        Scheme::Ed25519 => {
+            let pair = ed25519::Pair::from_string(suri, None).map_err(invalid)?;
+            (pair.public().into(), Box::new(move |payload| pair.sign(payload).into()))
+        },
+        // Signs the blake2 hash of the payload, which `MultiSignature` verifies against
+        Scheme::Ecdsa => {
+            let pair = ecdsa::Pair::from_string(suri, None).map_err(invalid)?;
+            (pair.public().into(), Box::new(move |payload| pair.sign(payload).into()))
+        },
+    })
+}
+
+// What senders sign. Its SCALE encoding has to match `transfer_core::transfer_payload`, which the
+// guest verifies signatures against
 #[derive(Deserialize, Debug, Decode, Encode)]
 pub struct UnsignedTransactionInput {
-    pub sender: Public,
-    pub recipient: Public,
+    // Genesis hash of the chain and id of the rollup pallet on it, so the signature is not valid on
+    // any other deployment
+    pub genesis_hash: [u8; 32],
+    pub rollup_id: [u8; 8],
+    pub sender: AccountId32,
+    pub recipient: AccountId32,
     pub amount: u128,
+    pub nonce: u64,
 }
 
-// Create a signed transaction for display only. Purely for convenience of local testing
-// pub fn display_signature(unsigned_tx: UnsignedTransactionInput, signer: String) {
-pub fn display_signature(signer: String, recipient: Public, amount: u128) {
-    let pair = Pair::from_string(&signer, None).unwrap();
+// Sign a transfer from the key's account for the given chain and rollup. Purely for convenience of
+// local testing, as senders would sign their own transfers
+pub fn sign_transfer(signer: &str, scheme: Scheme, recipient: AccountId32, amount: u128, nonce: u64, domain: ([u8; 32], [u8; 8])) -> Result<TransactionInput, ProverError> {
     // This is a bit of convenience: we'll assume the signer is sender.
-    let sender = pair.public();
-    let unsigned_tx = UnsignedTransactionInput { sender, recipient, amount };
-    let signature = pair.sign(unsigned_tx.encode().as_ref());
-
-    let tx = TransactionInput {
-        sender, recipient, signature, amount
+    let (sender, sign) = signer_pair(scheme, signer)?;
+    let unsigned_tx = UnsignedTransactionInput {
+        genesis_hash: domain.0,
+        rollup_id: domain.1,
+        sender: sender.clone().into_account(),
+        recipient: recipient.clone(),
+        amount,
+        nonce,
     };
+    let signature = sign(unsigned_tx.encode().as_ref());
 
-    let json_output = serde_json::to_string_pretty(&tx).unwrap();
+    Ok(TransactionInput {
+        sender, recipient, signature, amount, nonce
+    })
+}
 
-    println!("{}\n(Hint: Paste this in transactions.json)", json_output);
+// Next nonce of the signer according to the ledger. Transfers from it which are signed but not yet
+// applied also have to be counted, so pass the nonce explicitly when signing several
+pub fn sender_nonce(signer: &str, scheme: Scheme, ledger: &Ledger) -> Result<u64, ProverError> {
+    let (sender, _) = signer_pair(scheme, signer)?;
+    Ok(ledger.nonce(&sender.into_account()))
 }
 
-pub fn process_json_file(file_path: String) -> Vec<TransactionInput> {
-    let file_contents = std::fs::read_to_string(file_path).expect("Failed to read file");
-    let data: Vec<TransactionInput> = serde_json::from_str(&file_contents).expect("Failed to parse JSON");
-    data
-}
\ No newline at end of file
+pub fn process_json_file(file_path: &str) -> Result<Vec<TransactionInput>, ProverError> {
+    let file_contents = std::fs::read_to_string(file_path)?;
+    Ok(serde_json::from_str(&file_contents)?)
+}
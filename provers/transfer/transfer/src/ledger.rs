@@ -0,0 +1,83 @@
+use codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use subxt::ext::sp_core::crypto::AccountId32;
+use transfer_core::{leaf, merkle_proof, merkle_root, MerkleProof};
+
+use crate::error::ProverError;
+
+pub const DEFAULT_LEDGER_PATH: &str = "./ledger.json";
+
+// An account in the ledger
+#[derive(Clone, Copy, Debug, Default, Decode, Deserialize, Encode, Serialize)]
+pub struct LedgerAccount {
+    pub balance: u128,
+    // Nonce the next signed transfer from the account has to carry
+    pub nonce: u64,
+}
+
+// Local copy of the rollup ledger. The chain only stores its root, so the host rebuilds it from the
+// changes each batch lists in its `LedgerUpdated` event
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Ledger {
+    // Number of the next batch, which builds on these balances
+    pub batch_number: u64,
+    // Last finalized block whose events have been applied
+    pub synced_to: u32,
+    // Kept in the byte order of the account ids, which the root is computed over and the guest
+    // requires its input in
+    pub accounts: BTreeMap<AccountId32, LedgerAccount>,
+}
+
+impl Ledger {
+    // Load the ledger, or start from the empty ledger of a new chain if there is no file yet
+    pub fn load(path: &str) -> Result<Ledger, ProverError> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Ledger::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), ProverError> {
+        let contents = serde_json::to_string_pretty(self)?;
+        Ok(std::fs::write(path, contents)?)
+    }
+
+    fn leaves(&self) -> Vec<[u8; 32]> {
+        self.accounts.iter().map(|(account, entry)| leaf(account.as_ref(), entry.balance, entry.nonce)).collect()
+    }
+
+    // Same root as the guest and the pallet compute
+    pub fn root(&self) -> [u8; 32] {
+        merkle_root(self.leaves())
+    }
+
+    // Nonce the next signed transfer from the account has to carry
+    pub fn nonce(&self, account: &AccountId32) -> u64 {
+        self.accounts.get(account).map_or(0, |entry| entry.nonce)
+    }
+
+    // Balance and nonce of the account and the proof of them against the root, if it is in the ledger
+    pub fn proof(&self, account: &AccountId32) -> Option<(LedgerAccount, MerkleProof)> {
+        let index = self.accounts.keys().position(|key| key == account)?;
+        Some((self.accounts[account], merkle_proof(self.leaves(), index)))
+    }
+
+    // Apply the changes of a batch. Batches which are already applied are skipped, so events can be
+    // replayed safely
+    pub fn apply(&mut self, batch_number: u64, changes: Vec<([u8; 32], u128, u64)>) -> Result<(), ProverError> {
+        if batch_number < self.batch_number {
+            return Ok(());
+        }
+        if batch_number != self.batch_number {
+            return Err(ProverError::MissingBatches { batch_number });
+        }
+
+        for (account, balance, nonce) in changes {
+            self.accounts.insert(AccountId32::from(account), LedgerAccount { balance, nonce });
+        }
+        self.batch_number += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,20 @@
+//! Proves batches of rollup transfers in the transfer guest and submits them to the node. The
+//! `prover-host` binary is a thin command line wrapper around this
+
+pub mod bundle;
+pub mod config;
+pub mod error;
+pub mod input;
+pub mod ledger;
+pub mod prover;
+pub mod snapshot;
+
+pub use bundle::ReceiptBundle;
+pub use config::ProverConfig;
+pub use error::ProverError;
+pub use methods::TRANSFER_ID;
+pub use prover::{
+    substrate_node, Batch, ChainState, Journal, Prover, ProvenBatch, QueuedEntry, Submission, TransactionOutcome,
+    FIXTURE_BALANCE, FIXTURE_DOMAIN, FIXTURE_FEE, FIXTURE_PROVER,
+};
+pub use snapshot::StateSnapshot;
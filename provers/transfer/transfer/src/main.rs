@@ -12,29 +12,231 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-mod input;
 mod cli;
-mod tx_prover;
 
-use cli::{Cli, SubCommand::{Sign, Run}};
+use cli::{Cli, SignerArgs, SubCommand::{Sign, Run, Prove, Submit, Inspect, Sync, Snapshot, Escape, Fixture}};
 use clap::Parser;
+use risc_roll_prover::{
+    input,
+    Batch, ChainState, Journal, Prover, ProverConfig, ProverError, ProvenBatch, ReceiptBundle, StateSnapshot,
+    TransactionOutcome, FIXTURE_DOMAIN, FIXTURE_PROVER, TRANSFER_ID,
+};
+use std::time::{Duration, Instant};
+use subxt::ext::sp_core::{crypto::AccountId32, sr25519::Pair as SubxtPair, Pair as SubxtPairT};
 
 #[tokio::main]
 async fn main() {
-    let args = Cli::parse();
+    let mut args = Cli::parse();
+    // A bare invocation is a run with its defaults, parsed as one so the signer can still come
+    // from the environment
+    if args.command.is_none() {
+        args = Cli::parse_from(std::env::args().chain(["run".to_owned()]));
+    }
+    if let Err(err) = run(args).await {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+async fn run(args: Cli) -> Result<(), ProverError> {
+    let config = ProverConfig::load(args.config.as_deref())?.merge(ProverConfig {
+        node_url: args.node_url,
+        ..Default::default()
+    });
 
     match args.command {
-        Some(Sign { suri, to, amount  }) => {
+        Some(Sign { suri, scheme, to, amount, nonce, ledger, fixture }) => {
             // Signer mode for convenient transaction signing
-            input::display_signature(suri, to, amount)
+            let (nonce, domain) = if fixture {
+                // Fixtures are proven from fresh accounts, whose nonces all start at zero
+                (nonce.unwrap_or(0), FIXTURE_DOMAIN)
+            } else {
+                let prover = Prover::connect(config.node_url(), None, &ledger).await?;
+                let nonce = match nonce {
+                    Some(nonce) => nonce,
+                    // Sync first, so the nonce is not one a landed batch has already used
+                    None => input::sender_nonce(&suri, scheme, &prover.sync().await?)?,
+                };
+                (nonce, prover.signing_domain().await?)
+            };
+            let tx = input::sign_transfer(&suri, scheme, to, amount, nonce, domain)?;
+            println!("{}\n(Hint: Paste this in transactions.json)", serde_json::to_string_pretty(&tx)?);
         },
-        Some(Run { transactions_file_path, .. }) => {
+        Some(Run { transactions_file_path, ledger, unsigned, output, signer }) => {
             // Run the code
-            tx_prover::prove_transactions(transactions_file_path).await;
+            let prover = connect_prover(&config, signer, &ledger).await?;
+            let state = prover.load_state().await?;
+            let proven = prove_transactions(&prover, &state, &transactions_file_path, &output)?;
+            submit_batch(&prover, &proven, unsigned).await?;
+        },
+        Some(Prove { transactions_file_path, ledger, output, state_file, prover, signer }) => {
+            let (prover, state) = match state_file {
+                // No node needed, the snapshot has everything the batch builds on
+                Some(state_file) => {
+                    // Nothing is signed, so the prover account is enough and the secret can stay
+                    // away from the machine proving
+                    let account = match prover {
+                        Some(account) => account.into(),
+                        None => prover_key(&config, signer)?.public().0,
+                    };
+                    let prover = Prover::offline(account);
+                    (prover, StateSnapshot::load(&state_file)?.into_state()?)
+                },
+                None => {
+                    let prover = connect_prover(&config, signer, &ledger).await?;
+                    let state = prover.load_state().await?;
+                    (prover, state)
+                },
+            };
+            prove_transactions(&prover, &state, &transactions_file_path, &output)?;
+        },
+        Some(Submit { bundle, ledger, unsigned, signer }) => {
+            let config = config.merge(ProverConfig { signer_suri: signer.signer_suri, signer_keyfile: signer.signer_keyfile, ..Default::default() });
+            // Only signed submissions need a key, as the prover account is already in the proof
+            let signer = config.signer()?;
+            if signer.is_none() && !unsigned {
+                return Err(ProverError::NoSigner);
+            }
+            let prover = Prover::connect(config.node_url(), signer, &ledger).await?;
+            let proven = ReceiptBundle::load(&bundle)?.into_proven()?;
+            submit_batch(&prover, &proven, unsigned).await?;
+        },
+        Some(Inspect { bundle }) => {
+            let bundle = ReceiptBundle::load(&bundle)?;
+            let journal = bundle.journal()?;
+            show_bundle(&bundle, &journal);
+        },
+        Some(Sync { ledger }) => {
+            let ledger = Prover::connect(config.node_url(), None, &ledger).await?.sync().await?;
+            println!("Ledger is at batch {} with {} accounts", ledger.batch_number, ledger.accounts.len());
+        },
+        Some(Snapshot { block_hash, ledger, output }) => {
+            let prover = Prover::connect(config.node_url(), None, &ledger).await?;
+            let block_hash = match block_hash {
+                Some(block_hash) => block_hash,
+                None => prover.finalized_head().await?,
+            };
+            let state = prover.snapshot(block_hash).await?;
+            StateSnapshot::new(block_hash.0, &state).save(&output)?;
+            println!("Saved the state of batch {} at block {:?} with {} accounts to {}", state.batch_number, block_hash, state.ledger.accounts.len(), output);
+        },
+        Some(Escape { suri, ledger }) => {
+            let key = SubxtPair::from_string(&suri, None).map_err(|_| ProverError::InvalidSecret)?;
+            let prover = Prover::connect(config.node_url(), Some(key), &ledger).await?;
+            let balance = prover.escape().await?;
+            println!("Escaped with {}", balance);
         },
-        // TODO: I feel like I am not using clap default arguments properly here... I would think there would be a way to avoid to have to do it manually this
-        None => {
-            tx_prover::prove_transactions("./transactions.json".to_string()).await
-        }
+        Some(Fixture { transactions_file_path, segment_limit_po2, output }) => {
+            generate_fixture(&transactions_file_path, segment_limit_po2, &output)?;
+        },
+        // Parsed as a run in main
+        None => unreachable!(),
+    }
+    Ok(())
+}
+
+// The prover's share of the batch fees is paid to the signer, so there has to be one even for
+// unsigned submissions
+fn prover_key(config: &ProverConfig, signer: SignerArgs) -> Result<SubxtPair, ProverError> {
+    let config = config.clone().merge(ProverConfig { signer_suri: signer.signer_suri, signer_keyfile: signer.signer_keyfile, ..Default::default() });
+    config.signer()?.ok_or(ProverError::NoSigner)
+}
+
+async fn connect_prover(config: &ProverConfig, signer: SignerArgs, ledger_path: &str) -> Result<Prover, ProverError> {
+    Prover::connect(config.node_url(), Some(prover_key(config, signer)?), ledger_path).await
+}
+
+// Prove the transactions in the file on top of the state and save the receipt bundle, so the batch
+// is not lost if submitting it fails
+fn prove_transactions(prover: &Prover, state: &ChainState, file_path: &str, bundle_path: &str) -> Result<ProvenBatch, ProverError> {
+    println!("Preparing transactions...");
+    let transfers = input::process_json_file(file_path)?;
+    let batch = prover.build_batch(state, transfers)?;
+    let (proven, elapsed) = execute_and_prove(prover, &batch)?;
+    show_outcomes(&batch, &proven);
+
+    println!("transfer image id {:?} (if you updated guest, this needs to be registered in the pallet's program registry)", TRANSFER_ID);
+
+    ReceiptBundle::new(&proven, elapsed).save(bundle_path)?;
+    println!("Saved receipt bundle to {}", bundle_path);
+    Ok(proven)
+}
+
+async fn submit_batch(prover: &Prover, proven: &ProvenBatch, unsigned: bool) -> Result<(), ProverError> {
+    println!("Sending tx");
+    let submission = prover.submit(proven, unsigned).await?;
+    println!("Batch {} applied in block {:?}", submission.batch_number, submission.block_hash);
+    println!("Done");
+    Ok(())
+}
+
+// Prove a batch against a fixed starting ledger, and write it as a SCALE encoded (image id, receipt)
+// pair for the pallet's benchmarks and tests
+fn generate_fixture(file_path: &str, segment_limit_po2: Option<u32>, output: &str) -> Result<(), ProverError> {
+    let transfers = input::process_json_file(file_path)?;
+    let prover = Prover::offline(FIXTURE_PROVER).with_segment_limit(segment_limit_po2);
+    let batch = prover.build_batch(&ChainState::fixture(&transfers), transfers)?;
+    let (proven, _) = execute_and_prove(&prover, &batch)?;
+
+    println!("Writing fixture with {:?} segments to {}", proven.receipt.segments.len(), output);
+    std::fs::write(output, proven.fixture())?;
+    Ok(())
+}
+
+fn execute_and_prove(prover: &Prover, batch: &Batch) -> Result<(ProvenBatch, Duration), ProverError> {
+    for index in batch.bad_signatures.iter() {
+        let transfer = &batch.transfers[*index];
+        println!("Warning: transfer #{} is not signed by its sender {:?}", index, transfer.sender_account());
+    }
+
+    println!("Now running transfer txes in guest");
+    let guest_start_time = Instant::now();
+    let session = prover.execute(batch)?;
+    let proven = prover.prove(&session)?;
+    let elapsed = guest_start_time.elapsed();
+    println!("Guest done proving {:?} txes in {:?} sec {:?} ms", batch.transfers.len(), elapsed.as_secs(), elapsed.subsec_millis());
+    Ok((proven, elapsed))
+}
+
+// Print whether the guest applied or left out each transaction, as the pallet reports them in its
+// `TransferApplied` and `TransferRejected` events
+fn show_outcomes(batch: &Batch, proven: &ProvenBatch) {
+    let descriptions = batch.queued.iter().map(|(kind, sender, recipient, amount)| {
+        let kind = match *kind {
+            transfer_core::QUEUED_DEPOSIT => "deposit",
+            transfer_core::QUEUED_WITHDRAWAL => "withdrawal",
+            _ => "transfer",
+        };
+        format!("queued {} of {} from {:?} to {:?}", kind, amount, AccountId32::from(*sender), AccountId32::from(*recipient))
+    }).chain(batch.transfers.iter().map(|transfer| {
+        format!("transfer of {} from {:?} to {:?}", transfer.amount, transfer.sender_account(), transfer.recipient)
+    }));
+
+    for (index, (description, outcome)) in descriptions.zip(proven.outcomes()).enumerate() {
+        println!("#{} {}: {}", index, description, outcome);
+    }
+}
+
+// Print the metadata of a receipt bundle and the batch its journal commits
+fn show_bundle(bundle: &ReceiptBundle, journal: &Journal) {
+    let metadata = &bundle.metadata;
+    println!("Receipt bundle version {} for image id {:?}", bundle.version, bundle.image_id);
+    println!("Batch {} with hash {}, proven in {} ms at {} (unix time) in {} segments", metadata.batch_number, metadata.batch_hash, metadata.proving_time_ms, metadata.created_at, bundle.segments.len());
+    println!("State root {} -> {}", array_bytes::bytes2hex("0x", journal.3), array_bytes::bytes2hex("0x", journal.4));
+    println!("Genesis hash {}, rollup {}", array_bytes::bytes2hex("0x", journal.13), String::from_utf8_lossy(&journal.14));
+    println!("Prover {:?}, fee {} per transfer, {} in fees", AccountId32::from(journal.6), u128::from_be_bytes(journal.7), u128::from_be_bytes(journal.8));
+    println!("Processed {} queued entries with hash {}", journal.9, array_bytes::bytes2hex("0x", journal.10));
+
+    println!("Changed accounts:");
+    for ((account, balance), nonce) in journal.0.iter().zip(journal.1.iter()).zip(journal.2.iter()) {
+        println!("  {:?}: balance {}, nonce {}", AccountId32::from(*account), u128::from_be_bytes(*balance), nonce);
+    }
+    println!("Withdrawals:");
+    for (account, amount) in journal.11.iter() {
+        println!("  {:?}: {}", AccountId32::from(*account), u128::from_be_bytes(*amount));
+    }
+    println!("Outcomes:");
+    for (index, code) in journal.12.iter().enumerate() {
+        println!("  #{}: {}", index, TransactionOutcome::from(*code));
     }
 }
@@ -0,0 +1,496 @@
+use methods::{TRANSFER_ELF, TRANSFER_ID};
+use crate::{
+    error::ProverError,
+    input::TransactionInput,
+    ledger::{Ledger, LedgerAccount, DEFAULT_LEDGER_PATH},
+};
+
+use risc0_zkvm::{
+    serde::{from_slice, to_vec},
+    Executor, ExecutorEnv, SegmentReceipt, Session, SessionReceipt,
+};
+use subxt::{
+	ext::{
+		sp_core::{
+			crypto::AccountId32,
+			sr25519::Pair as SubxtPair,
+			Pair as SubxtPairT,
+			H256,
+		},
+	},
+	tx::PairSigner,
+	OnlineClient, PolkadotConfig,
+};
+use codec::{Decode, Encode};
+use std::fmt;
+use transfer_core::{
+    QUEUED_DEPOSIT, QUEUED_TRANSFER, QUEUED_WITHDRAWAL, TRANSFER_APPLIED, TRANSFER_BAD_NONCE, TRANSFER_BAD_SIGNATURE,
+    TRANSFER_INSUFFICIENT_BALANCE, TRANSFER_OVERFLOW,
+};
+
+// // Runtime types, etc
+#[subxt::subxt(runtime_metadata_path = "./metadata.scale")]
+pub mod substrate_node {}
+
+use substrate_node::runtime_types::pallet_template::common::{QueuedKind, Receipt};
+use substrate_node::template_module::events::{Escaped, LedgerUpdated};
+
+// Balance of every account in the ledger the fixtures are proven against
+pub const FIXTURE_BALANCE: u128 = 1 << 60;
+// Fee per transfer and prover account when generating fixtures, matching the pallet's mock runtime
+pub const FIXTURE_FEE: u128 = 1_000;
+pub const FIXTURE_PROVER: [u8; 32] = [0xaa; 32];
+// Genesis hash and rollup pallet id of the pallet's mock runtime, which fixture transfers are signed for
+pub const FIXTURE_DOMAIN: ([u8; 32], [u8; 8]) = ([69; 32], *b"py/rolup");
+
+// Journal committed by the guest, see `TransferJournal` in the pallet
+pub type Journal = (
+    Vec<[u8; 32]>,
+    Vec<[u8; 16]>,
+    Vec<u64>,
+    [u8; 32],
+    [u8; 32],
+    u64,
+    [u8; 32],
+    [u8; 16],
+    [u8; 16],
+    u64,
+    [u8; 32],
+    Vec<([u8; 32], [u8; 16])>,
+    Vec<u8>,
+    [u8; 32],
+    [u8; 8],
+);
+
+// A transfer posted on-chain: kind, sender, recipient and amount
+pub type QueuedEntry = (u8, [u8; 32], [u8; 32], u128);
+
+// What the guest did with a transaction, as the pallet reports it in its `TransferApplied` and
+// `TransferRejected` events
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransactionOutcome {
+    Applied,
+    InsufficientBalance,
+    Overflow,
+    BadSignature,
+    BadNonce,
+    Unknown(u8),
+}
+
+impl From<u8> for TransactionOutcome {
+    fn from(code: u8) -> Self {
+        match code {
+            TRANSFER_APPLIED => TransactionOutcome::Applied,
+            TRANSFER_INSUFFICIENT_BALANCE => TransactionOutcome::InsufficientBalance,
+            TRANSFER_OVERFLOW => TransactionOutcome::Overflow,
+            TRANSFER_BAD_SIGNATURE => TransactionOutcome::BadSignature,
+            TRANSFER_BAD_NONCE => TransactionOutcome::BadNonce,
+            code => TransactionOutcome::Unknown(code),
+        }
+    }
+}
+
+impl fmt::Display for TransactionOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransactionOutcome::Applied => write!(f, "applied"),
+            TransactionOutcome::InsufficientBalance => write!(f, "rejected, insufficient balance"),
+            TransactionOutcome::Overflow => write!(f, "rejected, balance overflow"),
+            TransactionOutcome::BadSignature => write!(f, "rejected, not signed by the sender"),
+            TransactionOutcome::BadNonce => write!(f, "rejected, not the sender's next nonce"),
+            TransactionOutcome::Unknown(code) => write!(f, "unknown outcome {}", code),
+        }
+    }
+}
+
+// Rollup state a batch builds on: what the chain committed to, and the local ledger matching it
+pub struct ChainState {
+    pub state_root: [u8; 32],
+    pub batch_number: u64,
+    // Senders pay this on top of every transfer, and the pallet rejects batches proven with any other fee
+    pub fee: u128,
+    // Only signatures for this chain and rollup are valid, and the pallet checks the batch was proven for them
+    pub domain: ([u8; 32], [u8; 8]),
+    // Transfers posted on-chain have to be processed before they get too old, so the whole queue
+    // goes into every batch
+    pub queued: Vec<QueuedEntry>,
+    pub ledger: Ledger,
+}
+
+impl ChainState {
+    // Fixed starting state the fixtures are proven against, where every account in the transfers
+    // holds `FIXTURE_BALANCE`
+    pub fn fixture(transfers: &[TransactionInput]) -> ChainState {
+        let mut ledger = Ledger::default();
+        for transfer in transfers.iter() {
+            ledger.accounts.insert(transfer.sender_account(), LedgerAccount { balance: FIXTURE_BALANCE, nonce: 0 });
+            ledger.accounts.insert(transfer.recipient.clone(), LedgerAccount { balance: FIXTURE_BALANCE, nonce: 0 });
+        }
+        ChainState {
+            state_root: ledger.root(),
+            batch_number: 0,
+            fee: FIXTURE_FEE,
+            domain: FIXTURE_DOMAIN,
+            queued: vec![],
+            ledger,
+        }
+    }
+
+    // Whether the ledger is the one the chain committed to
+    pub fn ledger_matches(&self) -> bool {
+        self.ledger.root() == self.state_root && self.ledger.batch_number == self.batch_number
+    }
+}
+
+// Guest input for a batch, along with the transactions it processes
+pub struct Batch {
+    pub queued: Vec<QueuedEntry>,
+    pub transfers: Vec<TransactionInput>,
+    // Indices of the transfers which are not signed by their sender. The guest leaves them out of
+    // the batch anyway, but it is cheaper to find out before proving
+    pub bad_signatures: Vec<usize>,
+    inputs: Vec<Vec<u32>>,
+}
+
+// A batch proven and verified against the transfer image id
+pub struct ProvenBatch {
+    pub receipt: SessionReceipt,
+    pub journal: Journal,
+}
+
+impl ProvenBatch {
+    // Outcome of every transaction, queued ones first
+    pub fn outcomes(&self) -> Vec<TransactionOutcome> {
+        self.journal.12.iter().map(|code| TransactionOutcome::from(*code)).collect()
+    }
+
+    // The receipt in a form SCALE can understand
+    fn substrate_receipt(&self) -> Receipt {
+        Receipt {
+            segments: self.receipt.segments.iter().map(|SegmentReceipt { seal, index }| {
+                (seal.clone(), *index)
+            }).collect(),
+            journal: self.receipt.journal.clone(),
+        }
+    }
+
+    // SCALE encoded (image id, receipt) pair, as the pallet's benchmarks and tests take fixtures
+    pub fn fixture(&self) -> Vec<u8> {
+        (TRANSFER_ID, self.substrate_receipt()).encode()
+    }
+}
+
+// A batch applied on-chain
+pub struct Submission {
+    pub block_hash: H256,
+    pub batch_number: u64,
+}
+
+// Proves batches of the rollup and submits them to the node, keeping the local ledger in step
+pub struct Prover {
+    api: Option<OnlineClient<PolkadotConfig>>,
+    // Key submitting batches and escapes
+    signer: Option<SubxtPair>,
+    // The prover's share of the batch fees is paid to this account
+    prover: Option<[u8; 32]>,
+    ledger_path: String,
+    // Smaller segments mean more of them for the same program
+    segment_limit_po2: Option<u32>,
+}
+
+impl Prover {
+    // Prover talking to the node at `node_url`, which signs with the key and is paid its share of the
+    // fees. Without a key it can only read from the node
+    pub async fn connect(node_url: &str, signer: Option<SubxtPair>, ledger_path: &str) -> Result<Prover, ProverError> {
+        let api = OnlineClient::<PolkadotConfig>::from_url(node_url).await?;
+        Ok(Prover {
+            api: Some(api),
+            prover: signer.as_ref().map(|pair| pair.public().0),
+            signer,
+            ledger_path: ledger_path.to_string(),
+            segment_limit_po2: None,
+        })
+    }
+
+    // Prover without a node, which can only build, execute and prove batches
+    pub fn offline(prover: [u8; 32]) -> Prover {
+        Prover {
+            api: None,
+            signer: None,
+            prover: Some(prover),
+            ledger_path: DEFAULT_LEDGER_PATH.to_string(),
+            segment_limit_po2: None,
+        }
+    }
+
+    pub fn with_segment_limit(mut self, segment_limit_po2: Option<u32>) -> Prover {
+        self.segment_limit_po2 = segment_limit_po2;
+        self
+    }
+
+    fn api(&self) -> Result<&OnlineClient<PolkadotConfig>, ProverError> {
+        self.api.as_ref().ok_or(ProverError::Offline)
+    }
+
+    fn signer(&self) -> Result<PairSigner<PolkadotConfig, SubxtPair>, ProverError> {
+        self.signer.clone().map(PairSigner::new).ok_or(ProverError::NoSigner)
+    }
+
+    fn prover_account(&self) -> Result<[u8; 32], ProverError> {
+        self.prover.ok_or(ProverError::NoSigner)
+    }
+
+    // Genesis hash of the chain and id of its rollup pallet, which transfers have to be signed for
+    pub async fn signing_domain(&self) -> Result<([u8; 32], [u8; 8]), ProverError> {
+        let api = self.api()?;
+        let rollup_id = api.constants().at(&substrate_node::constants().template_module().pallet_id())?;
+        Ok((api.genesis_hash().0, rollup_id.0))
+    }
+
+    // Latest rollup state committed on-chain, which the local ledger has to match
+    pub async fn load_state(&self) -> Result<ChainState, ProverError> {
+        let state = self.state_at(None, Ledger::load(&self.ledger_path)?).await?;
+        if !state.ledger_matches() {
+            return Err(ProverError::LedgerBehind { path: self.ledger_path.clone() });
+        }
+        Ok(state)
+    }
+
+    // Rollup state committed at the block, so batches can be proven against it without a node. The
+    // local ledger is caught up to the block if it is synced to an earlier one, and the ledger is only
+    // rebuilt from genesis if that does not match the state root, like when it is already past it
+    pub async fn snapshot(&self, at: H256) -> Result<ChainState, ProverError> {
+        let number = self.api()?.rpc().header(Some(at)).await?
+            .ok_or(ProverError::UnexpectedChainState("block has no header"))?
+            .number;
+
+        let mut ledger = Ledger::load(&self.ledger_path)?;
+        if ledger.synced_to <= number {
+            self.replay(&mut ledger, number).await?;
+        }
+        let state = self.state_at(Some(at), ledger).await?;
+        if state.ledger_matches() {
+            return Ok(state);
+        }
+
+        let mut ledger = Ledger::default();
+        self.replay(&mut ledger, number).await?;
+        let state = ChainState { ledger, ..state };
+        if !state.ledger_matches() {
+            return Err(ProverError::UnexpectedChainState("ledger updates do not add up to the state root"));
+        }
+        Ok(state)
+    }
+
+    // Hash of the last finalized block
+    pub async fn finalized_head(&self) -> Result<H256, ProverError> {
+        Ok(self.api()?.rpc().finalized_head().await?)
+    }
+
+    // Rollup state committed at the block, or the latest one, on top of the ledger
+    async fn state_at(&self, at: Option<H256>, ledger: Ledger) -> Result<ChainState, ProverError> {
+        let api = self.api()?;
+        let storage = substrate_node::storage().template_module();
+
+        // Queued transfers were authorized by the extrinsic that queued them
+        let queue = api.storage().fetch_or_default(&storage.transfer_queue(), at).await?;
+        let queued = queue.0.into_iter().map(|transfer| {
+            let kind = match transfer.kind {
+                QueuedKind::Transfer => QUEUED_TRANSFER,
+                QueuedKind::Deposit => QUEUED_DEPOSIT,
+                QueuedKind::Withdrawal => QUEUED_WITHDRAWAL,
+            };
+            (kind, transfer.sender, transfer.recipient, transfer.amount)
+        }).collect();
+
+        let state_root = api.storage().fetch_or_default(&storage.state_root(), at).await?;
+        let batch_number = api.storage().fetch_or_default(&storage.batch_number(), at).await?;
+
+        // The runtime may have been upgraded since the block, so the fee and the rollup id are read
+        // from the metadata of the runtime at the block
+        let metadata = api.rpc().metadata(at).await?;
+        let fee = template_constant(&metadata, "TransferFee")?;
+        let rollup_id: [u8; 8] = template_constant(&metadata, "PalletId")?;
+
+        Ok(ChainState {
+            state_root,
+            batch_number,
+            fee,
+            domain: (api.genesis_hash().0, rollup_id),
+            queued,
+            ledger,
+        })
+    }
+
+    // Guest input for a batch of the whole queue and the transfers on top of the state
+    pub fn build_batch(&self, state: &ChainState, transfers: Vec<TransactionInput>) -> Result<Batch, ProverError> {
+        if transfers.is_empty() && state.queued.is_empty() {
+            return Err(ProverError::EmptyBatch);
+        }
+        let bad_signatures = transfers.iter().enumerate()
+            .filter(|(_, transfer)| !transfer.verify(state.domain))
+            .map(|(index, _)| index)
+            .collect();
+
+        // The guest rebuilds the whole ledger to check it against the state root. The ledger is
+        // ordered, so the same batch always gives the same input and journal
+        let accounts: Vec<[u8; 32]> = state.ledger.accounts.keys().map(|account| account.clone().into()).collect();
+
+        // "compatible" here meaning u128s are converted to bytes for the vm to be able to use
+        let compatible_balances: Vec<[u8; 16]> = state.ledger.accounts.values().map(|entry| {
+            entry.balance.to_be_bytes()
+        }).collect();
+        let nonces: Vec<u64> = state.ledger.accounts.values().map(|entry| entry.nonce).collect();
+
+        let compatible_queued_transfers: Vec<(u8, [u8; 32], [u8; 32], [u8; 16])> = state.queued.iter().map(|(kind, sender, recipient, amount)| {
+            (*kind, *sender, *recipient, amount.to_be_bytes())
+        }).collect();
+
+        // The guest checks the signatures itself, so the proof shows every applied transfer was authorized
+        let compatible_transfers: Vec<(u8, Vec<u8>, [u8; 32], [u8; 16], u64, Vec<u8>)> = transfers.iter().map(|transfer| {
+            let (scheme, signer, signature) = transfer.signature_parts();
+            let recipient: [u8; 32] = transfer.recipient.clone().into();
+            (scheme, signer, recipient, transfer.amount.to_be_bytes(), transfer.nonce, signature)
+        }).collect();
+
+        let (genesis_hash, rollup_id) = state.domain;
+        let inputs = vec![
+            to_vec(&state.state_root)?,
+            to_vec(&state.batch_number)?,
+            to_vec(&self.prover_account()?)?,
+            to_vec(&state.fee.to_be_bytes())?,
+            to_vec(&genesis_hash)?,
+            to_vec(&rollup_id)?,
+            to_vec(&accounts)?,
+            to_vec(&compatible_balances)?,
+            to_vec(&nonces)?,
+            to_vec(&compatible_queued_transfers)?,
+            to_vec(&compatible_transfers)?,
+        ];
+
+        Ok(Batch { queued: state.queued.clone(), transfers, bad_signatures, inputs })
+    }
+
+    // Run the batch in the guest, without proving it
+    pub fn execute(&self, batch: &Batch) -> Result<Session, ProverError> {
+        let mut env_builder = ExecutorEnv::builder();
+        for input in batch.inputs.iter() {
+            env_builder.add_input(input);
+        }
+        if let Some(po2) = self.segment_limit_po2 {
+            env_builder.segment_limit_po2(po2);
+        }
+        let env = env_builder.build();
+
+        let mut exec = Executor::from_elf(env, TRANSFER_ELF)?;
+        Ok(exec.run()?)
+    }
+
+    // Prove an executed batch, and check the receipt verifies before anything is submitted
+    pub fn prove(&self, session: &Session) -> Result<ProvenBatch, ProverError> {
+        let receipt = session.prove()?;
+        receipt.verify(TRANSFER_ID).map_err(|err| ProverError::Verification(format!("{:?}", err)))?;
+        let journal = from_slice(&receipt.journal)?;
+        Ok(ProvenBatch { receipt, journal })
+    }
+
+    // Submit a proven batch and wait for it to be finalized, then apply it to the local ledger so
+    // the next batch can build on it. Unsigned submissions need no funded account, as the receipt is
+    // verified before the transaction enters the pool
+    pub async fn submit(&self, batch: &ProvenBatch, unsigned: bool) -> Result<Submission, ProverError> {
+        let api = self.api()?;
+        let progress = if unsigned {
+            let call = substrate_node::tx().template_module().submit_proof_unsigned(
+                TRANSFER_ID,
+                batch.substrate_receipt(),
+            );
+            api.tx().create_unsigned(&call)?.submit_and_watch().await?
+        } else {
+            let call = substrate_node::tx().template_module().submit_proof(
+                TRANSFER_ID,
+                batch.substrate_receipt(),
+            );
+            api.tx().sign_and_submit_then_watch_default(&call, &self.signer()?).await?
+        };
+        let events = progress.wait_for_finalized_success().await?;
+
+        let LedgerUpdated { batch_number, changes, .. } = events.find_first::<LedgerUpdated>()?
+            .ok_or(ProverError::UnexpectedChainState("applied batch did not update the ledger"))?;
+        let mut ledger = Ledger::load(&self.ledger_path)?;
+        ledger.apply(batch_number, changes)?;
+        ledger.save(&self.ledger_path)?;
+
+        Ok(Submission { block_hash: events.block_hash(), batch_number })
+    }
+
+    // Bring the local ledger up to date with the batches applied on-chain, by replaying the
+    // `LedgerUpdated` events of every finalized block since the last sync
+    pub async fn sync(&self) -> Result<Ledger, ProverError> {
+        let api = self.api()?;
+        let mut ledger = Ledger::load(&self.ledger_path)?;
+
+        let finalized = api.rpc().finalized_head().await?;
+        let head = api.rpc().header(Some(finalized)).await?
+            .ok_or(ProverError::UnexpectedChainState("finalized block has no header"))?
+            .number;
+        self.replay(&mut ledger, head).await?;
+
+        ledger.save(&self.ledger_path)?;
+        Ok(ledger)
+    }
+
+    // Apply the `LedgerUpdated` events of the blocks after the ledger's last synced one, up to `to`
+    async fn replay(&self, ledger: &mut Ledger, to: u32) -> Result<(), ProverError> {
+        let api = self.api()?;
+        for number in ledger.synced_to + 1..=to {
+            let hash = api.rpc().block_hash(Some(number.into())).await?
+                .ok_or(ProverError::UnexpectedChainState("finalized block has no hash"))?;
+            let events = api.events().at(Some(hash)).await?;
+            for event in events.find::<LedgerUpdated>() {
+                let LedgerUpdated { batch_number, changes, .. } = event?;
+                ledger.apply(batch_number, changes)?;
+            }
+            ledger.synced_to = number;
+        }
+        Ok(())
+    }
+
+    // Exit the rollup with the whole balance of the signer's account in the ledger, proven against
+    // the last state root, along with its deposits still in the queue. An account the ledger never
+    // credited exits with just those deposits. The pallet only allows this once no batch has landed
+    // for `EscapeTimeout` blocks. Returns the amount escaped with
+    pub async fn escape(&self) -> Result<u128, ProverError> {
+        let api = self.api()?;
+        let signer = self.signer()?;
+        let account = AccountId32::from(self.prover_account()?);
+
+        let state_root = api.storage().fetch_or_default(&substrate_node::storage().template_module().state_root(), None).await?;
+        let ledger = Ledger::load(&self.ledger_path)?;
+        if ledger.root() != state_root {
+            return Err(ProverError::LedgerBehind { path: self.ledger_path.clone() });
+        }
+
+        let progress = match ledger.proof(&account) {
+            Some((LedgerAccount { balance, nonce }, proof)) => {
+                let call = substrate_node::tx().template_module().escape(balance, nonce, proof);
+                api.tx().sign_and_submit_then_watch_default(&call, &signer).await?
+            },
+            None => {
+                let call = substrate_node::tx().template_module().escape_deposits();
+                api.tx().sign_and_submit_then_watch_default(&call, &signer).await?
+            },
+        };
+        let events = progress.wait_for_finalized_success().await?;
+        let Escaped { amount, .. } = events.find_first::<Escaped>()?
+            .ok_or(ProverError::UnexpectedChainState("escape did not pay out"))?;
+        Ok(amount)
+    }
+}
+
+// Constant of the rollup pallet, decoded from the metadata it is in
+fn template_constant<V: Decode>(metadata: &subxt::Metadata, name: &str) -> Result<V, ProverError> {
+    let constant = metadata.pallet("TemplateModule")
+        .and_then(|pallet| pallet.constant(name))
+        .map_err(subxt::Error::from)?;
+    V::decode(&mut constant.value()).map_err(|_| ProverError::UnexpectedChainState("rollup pallet constant does not decode"))
+}
@@ -0,0 +1,86 @@
+use codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use subxt::ext::sp_core::crypto::AccountId32;
+
+use crate::{
+    error::ProverError,
+    ledger::{Ledger, LedgerAccount},
+    prover::{ChainState, QueuedEntry},
+};
+
+pub const DEFAULT_SNAPSHOT_PATH: &str = "./state.json";
+
+// Rollup state at a block, saved to a file so batches can be proven against it with no node
+// running, as in CI runs and benchmarks. Files ending in `.scale` are SCALE encoded, and any other
+// ones are JSON
+#[derive(Debug, Decode, Deserialize, Encode, Serialize)]
+pub struct StateSnapshot {
+    // Block the state was taken at
+    pub block_hash: [u8; 32],
+    pub state_root: [u8; 32],
+    pub batch_number: u64,
+    pub fee: u128,
+    pub genesis_hash: [u8; 32],
+    pub rollup_id: [u8; 8],
+    pub queued: Vec<QueuedEntry>,
+    // The ledger matching the state root
+    pub accounts: Vec<(AccountId32, LedgerAccount)>,
+}
+
+impl StateSnapshot {
+    pub fn new(block_hash: [u8; 32], state: &ChainState) -> StateSnapshot {
+        StateSnapshot {
+            block_hash,
+            state_root: state.state_root,
+            batch_number: state.batch_number,
+            fee: state.fee,
+            genesis_hash: state.domain.0,
+            rollup_id: state.domain.1,
+            queued: state.queued.clone(),
+            accounts: state.ledger.accounts.iter().map(|(account, entry)| (account.clone(), *entry)).collect(),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), ProverError> {
+        let contents = if is_scale(path) {
+            self.encode()
+        } else {
+            serde_json::to_vec_pretty(self)?
+        };
+        Ok(std::fs::write(path, contents)?)
+    }
+
+    pub fn load(path: &str) -> Result<StateSnapshot, ProverError> {
+        let contents = std::fs::read(path)?;
+        if is_scale(path) {
+            StateSnapshot::decode(&mut &contents[..]).map_err(|_| ProverError::InvalidSnapshot("not a SCALE encoded snapshot"))
+        } else {
+            Ok(serde_json::from_slice(&contents)?)
+        }
+    }
+
+    // The state to prove against. The ledger has to match the state root, or the guest would reject it
+    pub fn into_state(self) -> Result<ChainState, ProverError> {
+        let ledger = Ledger {
+            batch_number: self.batch_number,
+            synced_to: 0,
+            accounts: self.accounts.into_iter().collect(),
+        };
+        let state = ChainState {
+            state_root: self.state_root,
+            batch_number: self.batch_number,
+            fee: self.fee,
+            domain: (self.genesis_hash, self.rollup_id),
+            queued: self.queued,
+            ledger,
+        };
+        if !state.ledger_matches() {
+            return Err(ProverError::InvalidSnapshot("ledger does not match the state root"));
+        }
+        Ok(state)
+    }
+}
+
+fn is_scale(path: &str) -> bool {
+    path.ends_with(".scale")
+}
@@ -15,9 +15,10 @@ use sp_core::{crypto::KeyTypeId, OpaqueMetadata};
 use sp_runtime::{
 	create_runtime_str, generic, impl_opaque_keys,
 	traits::{
-		AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount, NumberFor, One, Verify,
+		AccountIdConversion, AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount,
+		NumberFor, One, Verify,
 	},
-	transaction_validity::{TransactionSource, TransactionValidity},
+	transaction_validity::{TransactionPriority, TransactionSource, TransactionValidity},
 	ApplyExtrinsicResult, MultiSignature,
 };
 use sp_std::prelude::*;
@@ -37,7 +38,7 @@ pub use frame_support::{
 		},
 		IdentityFee, Weight,
 	},
-	StorageValue,
+	PalletId, StorageValue,
 };
 pub use frame_system::Call as SystemCall;
 pub use pallet_balances::Call as BalancesCall;
@@ -276,10 +277,33 @@ impl pallet_sudo::Config for Runtime {
 	type RuntimeCall = RuntimeCall;
 }
 
+parameter_types! {
+	pub const RollupPalletId: PalletId = PalletId(*b"py/rolup");
+	pub RollupTreasuryAccount: AccountId =
+		RollupPalletId::get().into_sub_account_truncating(*b"treasury");
+	pub const ProverRewardShare: Perbill = Perbill::from_percent(80);
+}
+
 /// Configure the pallet-template in pallets/template.
 impl pallet_template::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
 	type Currency = Balances;
+	type PalletId = RollupPalletId;
+	type AdminOrigin = frame_system::EnsureRoot<AccountId>;
+	type ProgramHandlers = (pallet_template::TransferHandler<Runtime>,);
+	type WeightInfo = pallet_template::weights::SubstrateWeight<Runtime>;
+	type UnsignedPriority = ConstU64<{ TransactionPriority::MAX / 2 }>;
+	type UnsignedLongevity = ConstU64<{ 10 * MINUTES as u64 }>;
+	type TransferFee = ConstU128<{ 10 * EXISTENTIAL_DEPOSIT }>;
+	type ProverRewardShare = ProverRewardShare;
+	type TreasuryAccount = RollupTreasuryAccount;
+	type MaxSequencers = ConstU32<32>;
+	type SequencerSlotLength = ConstU32<{ 10 * MINUTES }>;
+	type SequencerFallbackTimeout = ConstU32<{ HOURS }>;
+	type MaxQueuedTransfers = ConstU32<256>;
+	type QueueDeposit = ConstU128<{ 100 * EXISTENTIAL_DEPOSIT }>;
+	type ForcedInclusionDelay = ConstU32<{ 30 * MINUTES }>;
+	type EscapeTimeout = ConstU32<{ DAYS }>;
 }
 
 // Create the runtime by composing the FRAME pallets that were previously configured.